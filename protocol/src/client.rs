@@ -0,0 +1,285 @@
+use anyhow::{bail, Context, Result};
+use postcard::{from_bytes, to_stdvec};
+use std::io::prelude::*;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+use crate::{
+    BatteryReadout, ButtonsState, CellState, DistanceSensor, Heading, LedColor, MazeRunnerRequest,
+    MazeRunnerResponse, MotionProfile, MotionReadout, MoveStatus, TurnDirection, TurnKind,
+    DISPLAY_COLUMNS, SOCKET,
+};
+
+pub struct MazeRunnerClient {
+    stream: UnixStream,
+}
+
+impl MazeRunnerClient {
+    pub fn connect() -> Result<Self> {
+        Self::connect_to(SOCKET)
+    }
+
+    pub fn connect_to<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let stream = UnixStream::connect(path).context("Failed to connect to simulator")?;
+
+        Ok(Self { stream })
+    }
+
+    pub fn request(&mut self, request: MazeRunnerRequest) -> Result<MazeRunnerResponse> {
+        let request_buffer: Vec<u8> = to_stdvec(&request).context("Failed to serialize request")?;
+
+        self.stream
+            .write_all(request_buffer.as_slice())
+            .context("Failed to send request")?;
+
+        self.stream.flush().context("Could not flush the stream")?;
+
+        let mut buffer = [0; 100];
+
+        let n = self
+            .stream
+            .read(&mut buffer[..])
+            .context("Failed to receive response")?;
+
+        if n == 0 {
+            bail!("Simulator closed the connection");
+        }
+
+        from_bytes(&buffer[..n]).context("Failed to deserialize response")
+    }
+
+    pub fn initialize(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::Initialize)
+    }
+
+    pub fn move_forward(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveForward)
+    }
+
+    pub fn move_forward_cells(&mut self, cells: u8) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveForwardCells { cells })
+    }
+
+    pub fn move_half_cell(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveHalfCell)
+    }
+
+    pub fn move_forward_continuous(&mut self, half_cells: u8) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveForwardContinuous { half_cells })
+    }
+
+    pub fn rotate_left_90(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::RotateLeft90)
+    }
+
+    pub fn rotate_right_90(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::RotateRight90)
+    }
+
+    pub fn move_diagonal(&mut self, steps: u8) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveDiagonal { steps })
+    }
+
+    pub fn move_profiled(&mut self, steps: u8, profile: MotionProfile) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::MoveProfiled { steps, profile })
+    }
+
+    pub fn rotate_90_profiled(
+        &mut self,
+        direction: TurnDirection,
+        profile: MotionProfile,
+    ) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::Rotate90Profiled { direction, profile })
+    }
+
+    pub fn turn(&mut self, kind: TurnKind, direction: TurnDirection) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::Turn { kind, direction })
+    }
+
+    pub fn start_move(&mut self, steps: u8, profile: MotionProfile) -> Result<u32> {
+        self.request_move_id(MazeRunnerRequest::StartMove { steps, profile })
+    }
+
+    pub fn start_turn(&mut self, kind: TurnKind, direction: TurnDirection) -> Result<u32> {
+        self.request_move_id(MazeRunnerRequest::StartTurn { kind, direction })
+    }
+
+    pub fn start_rotate_90(
+        &mut self,
+        direction: TurnDirection,
+        profile: MotionProfile,
+    ) -> Result<u32> {
+        self.request_move_id(MazeRunnerRequest::StartRotate90 { direction, profile })
+    }
+
+    pub fn move_status(&mut self, id: u32) -> Result<MoveStatus> {
+        self.request_move_status(MazeRunnerRequest::GetMoveStatus { id })
+    }
+
+    pub fn cancel_move(&mut self, id: u32) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::CancelMove { id })
+    }
+
+    pub fn wait_move(&mut self, id: u32) -> Result<MoveStatus> {
+        self.request_move_status(MazeRunnerRequest::WaitMove { id })
+    }
+
+    pub fn poll_move_completion(&mut self) -> Result<Option<(u32, MoveStatus)>> {
+        match self.request(MazeRunnerRequest::PollMoveCompletion)? {
+            MazeRunnerResponse::MoveCompleted(completion) => Ok(completion),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn place_runner(&mut self, x: usize, y: usize, heading: Heading) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::PlaceRunner { x, y, heading })
+    }
+
+    pub fn set_pose(&mut self, x: f64, y: f64, theta: f64) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::SetPose { x, y, theta })
+    }
+
+    pub fn set_led(&mut self, index: u8, on: bool) -> Result<()> {
+        let color = if on { LedColor::ON } else { LedColor::OFF };
+
+        self.set_led_color(index, color)
+    }
+
+    pub fn set_led_color(&mut self, index: u8, color: LedColor) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::SetLed { index, color })
+    }
+
+    /// Duration in [ms]
+    pub fn beep(&mut self, frequency: u16, duration: u16) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::Beep {
+            frequency,
+            duration,
+        })
+    }
+
+    /// Text longer than the display is cut off
+    pub fn display_line(&mut self, line: u8, text: &str) -> Result<()> {
+        let mut buffer = [b' '; DISPLAY_COLUMNS];
+
+        for (byte, character) in buffer.iter_mut().zip(text.bytes()) {
+            *byte = character;
+        }
+
+        self.request_ack(MazeRunnerRequest::SetDisplayLine { line, text: buffer })
+    }
+
+    pub fn clear_display(&mut self) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::ClearDisplay)
+    }
+
+    pub fn wall_front(&mut self) -> Result<bool> {
+        self.request_wall(MazeRunnerRequest::GetWallFront)
+    }
+
+    pub fn wall_left(&mut self) -> Result<bool> {
+        self.request_wall(MazeRunnerRequest::GetWallLeft)
+    }
+
+    pub fn wall_right(&mut self) -> Result<bool> {
+        self.request_wall(MazeRunnerRequest::GetWallRight)
+    }
+
+    pub fn buttons(&mut self) -> Result<ButtonsState> {
+        match self.request(MazeRunnerRequest::GetButtonsState)? {
+            MazeRunnerResponse::Buttons(buttons) => Ok(buttons),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn update_cell_state(&mut self, x: usize, y: usize, state: CellState) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::UpdateCellState { x, y, state })
+    }
+
+    pub fn clear_cell(&mut self, x: usize, y: usize) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::ClearCell { x, y })
+    }
+
+    pub fn update_cell_value(&mut self, x: usize, y: usize, value: i32) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::UpdateCellValue { x, y, value })
+    }
+
+    pub fn distance(&mut self, sensor: DistanceSensor) -> Result<u16> {
+        match self.request(MazeRunnerRequest::GetDistanceReadout { sensor })? {
+            MazeRunnerResponse::Distance(distance) => Ok(distance),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn motion(&mut self) -> Result<MotionReadout> {
+        match self.request(MazeRunnerRequest::GetMotionReadout)? {
+            MazeRunnerResponse::Motion(motion) => Ok(motion),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn battery(&mut self) -> Result<BatteryReadout> {
+        match self.request(MazeRunnerRequest::GetBatteryReadout)? {
+            MazeRunnerResponse::Battery(battery) => Ok(battery),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn set_velocity(&mut self, translational: f64, rotational: f64) -> Result<()> {
+        self.request_ack(MazeRunnerRequest::SetVelocity {
+            translational,
+            rotational,
+        })
+    }
+
+    pub fn goal_cell(&mut self, index: usize) -> Result<Option<(usize, usize)>> {
+        match self.request(MazeRunnerRequest::GetGoalCell { index })? {
+            MazeRunnerResponse::GoalCell(cell) => Ok(cell),
+            response => unexpected(response),
+        }
+    }
+
+    pub fn goal_cells(&mut self) -> Result<Vec<(usize, usize)>> {
+        let mut cells = Vec::new();
+
+        while let Some(cell) = self.goal_cell(cells.len())? {
+            cells.push(cell);
+        }
+
+        Ok(cells)
+    }
+
+    fn request_ack(&mut self, request: MazeRunnerRequest) -> Result<()> {
+        match self.request(request)? {
+            MazeRunnerResponse::Ack => Ok(()),
+            response => unexpected(response),
+        }
+    }
+
+    fn request_move_id(&mut self, request: MazeRunnerRequest) -> Result<u32> {
+        match self.request(request)? {
+            MazeRunnerResponse::MoveStarted(id) => Ok(id),
+            response => unexpected(response),
+        }
+    }
+
+    fn request_move_status(&mut self, request: MazeRunnerRequest) -> Result<MoveStatus> {
+        match self.request(request)? {
+            MazeRunnerResponse::MoveStatus(status) => Ok(status),
+            response => unexpected(response),
+        }
+    }
+
+    fn request_wall(&mut self, request: MazeRunnerRequest) -> Result<bool> {
+        match self.request(request)? {
+            MazeRunnerResponse::WallDetected(wall) => Ok(wall),
+            response => unexpected(response),
+        }
+    }
+}
+
+fn unexpected<T>(response: MazeRunnerResponse) -> Result<T> {
+    match response {
+        MazeRunnerResponse::Error => bail!("Simulator rejected the request"),
+        response => bail!("Unexpected response: {:?}", response),
+    }
+}
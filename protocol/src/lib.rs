@@ -0,0 +1,434 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+mod client;
+
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+pub use client::MazeRunnerClient;
+
+pub const SOCKET: &str = "/tmp/micromouse_simulator_socket";
+
+pub const DISPLAY_LINES: usize = 4;
+pub const DISPLAY_COLUMNS: usize = 21;
+
+bitflags! {
+    #[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[serde(transparent)]
+    pub struct ButtonsState: u8 {
+        const Reset = 0b00000001;
+        const Button1 = 0b00000010;
+        const Button2 = 0b00000100;
+        const Button3 = 0b00001000;
+        const Button4 = 0b00010000;
+    }
+}
+
+bitflags! {
+    #[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[serde(transparent)]
+    pub struct CellState: u8 {
+        const NorthWall = 0b00000001;
+        const EastWall = 0b00000010;
+        const SouthWall = 0b00000100;
+        const WestWall = 0b00001000;
+        const Visited = 0b00010000;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum DistanceSensor {
+    FrontLeft,
+    FrontRight,
+    DiagonalLeft,
+    DiagonalRight,
+}
+
+/// Trapezoidal speed profile, in [mm/s] and [mm/s^2] for moves and [rad/s] and [rad/s^2] for
+/// rotations
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    pub max_speed: f64,
+    pub acceleration: f64,
+    /// Moves ending above zero keep going like continuous moves, rotations always stop
+    pub end_speed: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TurnKind {
+    /// 90 degree arc from a wall midpoint to the next one
+    Search90,
+    /// Half a turn in place, or into the cell ahead and back when on a wall midpoint
+    Turnaround180,
+    /// Between a straight from a cell center and a diagonal
+    Fast45,
+    /// Between a straight from a cell center and a diagonal
+    Fast135,
+    /// 90 degree arc of one cell radius from a cell center to a cell center
+    Large90,
+    /// Half circle around a post from a wall midpoint to the neighbouring one
+    Large180,
+    /// Between two diagonals at a wall midpoint
+    Diagonal90,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TurnDirection {
+    Left,
+    Right,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LedColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl LedColor {
+    pub const OFF: Self = Self {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+
+    /// Color of an LED switched on without picking one
+    pub const ON: Self = Self {
+        red: 0,
+        green: 255,
+        blue: 0,
+    };
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Heading {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+/// Progress of a move started with one of the `Start` requests
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MoveStatus {
+    Queued,
+    /// Share of the move already covered, from 0 to 1
+    Running(f32),
+    Done,
+    /// Stopped in front of a wall, or the turn would have hit one
+    Crashed,
+    Cancelled,
+    /// Not possible from where the runner was when the move came up
+    Rejected,
+    /// Not a move id the simulator knows about, or one forgotten since
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MotionReadout {
+    pub x: i32,
+    pub y: i32,
+    pub theta: f64,
+    pub velocity_translational: f64,
+    pub velocity_rotational: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatteryReadout {
+    /// Voltage under the present load [V]
+    pub voltage: f64,
+    /// [A]
+    pub current: f64,
+    /// Motors stay off once the voltage fell below the cutoff, until the battery is charged
+    pub cut_off: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MazeRunnerRequest {
+    /// Puts the runner back on the start cell with a charged battery
+    Initialize,
+    MoveForward,
+    RotateRight90,
+    RotateLeft90,
+    GetWallFront,
+    GetWallRight,
+    GetWallLeft,
+    GetButtonsState,
+    UpdateCellState {
+        x: usize,
+        y: usize,
+        state: CellState,
+    },
+    ClearCell {
+        x: usize,
+        y: usize,
+    },
+    UpdateCellValue {
+        x: usize,
+        y: usize,
+        value: i32,
+    },
+    GetDistanceReadout {
+        sensor: DistanceSensor,
+    },
+    GetMotionReadout,
+    SetVelocity {
+        translational: f64,
+        rotational: f64,
+    },
+    GetGoalCell {
+        index: usize,
+    },
+    MoveForwardCells {
+        cells: u8,
+    },
+    MoveHalfCell,
+    /// Moves forward and keeps going at speed until the next request arrives
+    MoveForwardContinuous {
+        half_cells: u8,
+    },
+    Turn {
+        kind: TurnKind,
+        direction: TurnDirection,
+    },
+    /// Moves along a diagonal from one wall midpoint to the next
+    MoveDiagonal {
+        steps: u8,
+    },
+    /// Moves along the current heading by half cells, or wall midpoints on a diagonal
+    MoveProfiled {
+        steps: u8,
+        profile: MotionProfile,
+    },
+    Rotate90Profiled {
+        direction: TurnDirection,
+        profile: MotionProfile,
+    },
+    /// Queues a move along the current heading and answers right away with its id
+    StartMove {
+        steps: u8,
+        profile: MotionProfile,
+    },
+    StartTurn {
+        kind: TurnKind,
+        direction: TurnDirection,
+    },
+    StartRotate90 {
+        direction: TurnDirection,
+        profile: MotionProfile,
+    },
+    GetMoveStatus {
+        id: u32,
+    },
+    /// Drops a queued move, a running straight brakes to the next half cell
+    CancelMove {
+        id: u32,
+    },
+    /// Blocks until the move has finished
+    WaitMove {
+        id: u32,
+    },
+    /// Takes the oldest finished move off the completion queue
+    PollMoveCompletion,
+    /// Stops the runner and puts it down in the center of a cell, ending the current run
+    PlaceRunner {
+        x: usize,
+        y: usize,
+        heading: Heading,
+    },
+    /// Like `PlaceRunner` with a pose in [mm] and [deg], snapped to the nearest cell center or
+    /// wall midpoint and 45 degree heading
+    SetPose {
+        x: f64,
+        y: f64,
+        theta: f64,
+    },
+    SetLed {
+        index: u8,
+        color: LedColor,
+    },
+    /// Plays a tone in [Hz] for a duration in [ms] without waiting for it to end, a zero
+    /// frequency silences the buzzer
+    Beep {
+        frequency: u16,
+        duration: u16,
+    },
+    /// ASCII text, padded with spaces
+    SetDisplayLine {
+        line: u8,
+        text: [u8; DISPLAY_COLUMNS],
+    },
+    ClearDisplay,
+    GetBatteryReadout,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MazeRunnerResponse {
+    Ack,
+    Error,
+    WallDetected(bool),
+    Buttons(ButtonsState),
+    Distance(u16),
+    Motion(MotionReadout),
+    GoalCell(Option<(usize, usize)>),
+    MoveStarted(u32),
+    MoveStatus(MoveStatus),
+    MoveCompleted(Option<(u32, MoveStatus)>),
+    Battery(BatteryReadout),
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use core::fmt::Debug;
+
+    use postcard::{from_bytes, to_stdvec};
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    /// Neither message derives `PartialEq`, the debug output stands in for comparing them
+    fn assert_round_trip<T: Serialize + DeserializeOwned + Debug>(message: T) {
+        let bytes = to_stdvec(&message).unwrap();
+        let decoded: T = from_bytes(&bytes).unwrap();
+
+        assert_eq!(format!("{decoded:?}"), format!("{message:?}"));
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let profile = MotionProfile {
+            max_speed: 1500.0,
+            acceleration: 5000.0,
+            end_speed: 300.0,
+        };
+
+        let mut text = [b' '; DISPLAY_COLUMNS];
+        text[..5].copy_from_slice(b"hello");
+
+        let requests = [
+            MazeRunnerRequest::Initialize,
+            MazeRunnerRequest::MoveForward,
+            MazeRunnerRequest::RotateRight90,
+            MazeRunnerRequest::RotateLeft90,
+            MazeRunnerRequest::GetWallFront,
+            MazeRunnerRequest::GetWallRight,
+            MazeRunnerRequest::GetWallLeft,
+            MazeRunnerRequest::GetButtonsState,
+            MazeRunnerRequest::UpdateCellState {
+                x: 3,
+                y: 12,
+                state: CellState::NorthWall | CellState::Visited,
+            },
+            MazeRunnerRequest::ClearCell { x: 15, y: 0 },
+            MazeRunnerRequest::UpdateCellValue {
+                x: 7,
+                y: 8,
+                value: -42,
+            },
+            MazeRunnerRequest::GetDistanceReadout {
+                sensor: DistanceSensor::DiagonalRight,
+            },
+            MazeRunnerRequest::GetMotionReadout,
+            MazeRunnerRequest::SetVelocity {
+                translational: 250.5,
+                rotational: -90.0,
+            },
+            MazeRunnerRequest::GetGoalCell { index: 3 },
+            MazeRunnerRequest::MoveForwardCells { cells: 4 },
+            MazeRunnerRequest::MoveHalfCell,
+            MazeRunnerRequest::MoveForwardContinuous { half_cells: 6 },
+            MazeRunnerRequest::Turn {
+                kind: TurnKind::Large180,
+                direction: TurnDirection::Left,
+            },
+            MazeRunnerRequest::MoveDiagonal { steps: 5 },
+            MazeRunnerRequest::MoveProfiled { steps: 8, profile },
+            MazeRunnerRequest::Rotate90Profiled {
+                direction: TurnDirection::Right,
+                profile,
+            },
+            MazeRunnerRequest::StartMove { steps: 2, profile },
+            MazeRunnerRequest::StartTurn {
+                kind: TurnKind::Diagonal90,
+                direction: TurnDirection::Right,
+            },
+            MazeRunnerRequest::StartRotate90 {
+                direction: TurnDirection::Left,
+                profile,
+            },
+            MazeRunnerRequest::GetMoveStatus { id: 7 },
+            MazeRunnerRequest::CancelMove { id: u32::MAX },
+            MazeRunnerRequest::WaitMove { id: 1 },
+            MazeRunnerRequest::PollMoveCompletion,
+            MazeRunnerRequest::PlaceRunner {
+                x: 1,
+                y: 2,
+                heading: Heading::SouthWest,
+            },
+            MazeRunnerRequest::SetPose {
+                x: 270.0,
+                y: 90.0,
+                theta: 45.0,
+            },
+            MazeRunnerRequest::SetLed {
+                index: 2,
+                color: LedColor {
+                    red: 255,
+                    green: 128,
+                    blue: 0,
+                },
+            },
+            MazeRunnerRequest::Beep {
+                frequency: 440,
+                duration: 250,
+            },
+            MazeRunnerRequest::SetDisplayLine { line: 1, text },
+            MazeRunnerRequest::ClearDisplay,
+            MazeRunnerRequest::GetBatteryReadout,
+        ];
+
+        for request in requests {
+            assert_round_trip(request);
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let responses = [
+            MazeRunnerResponse::Ack,
+            MazeRunnerResponse::Error,
+            MazeRunnerResponse::WallDetected(true),
+            MazeRunnerResponse::Buttons(ButtonsState::Reset | ButtonsState::Button3),
+            MazeRunnerResponse::Distance(1234),
+            MazeRunnerResponse::Motion(MotionReadout {
+                x: -15,
+                y: 2700,
+                theta: 135.0,
+                velocity_translational: 812.25,
+                velocity_rotational: -0.5,
+            }),
+            MazeRunnerResponse::GoalCell(Some((7, 8))),
+            MazeRunnerResponse::GoalCell(None),
+            MazeRunnerResponse::MoveStarted(3),
+            MazeRunnerResponse::MoveStatus(MoveStatus::Running(0.25)),
+            MazeRunnerResponse::MoveStatus(MoveStatus::Rejected),
+            MazeRunnerResponse::MoveCompleted(Some((3, MoveStatus::Crashed))),
+            MazeRunnerResponse::MoveCompleted(None),
+            MazeRunnerResponse::Battery(BatteryReadout {
+                voltage: 7.4,
+                current: 1.25,
+                cut_off: false,
+            }),
+        ];
+
+        for response in responses {
+            assert_round_trip(response);
+        }
+    }
+}
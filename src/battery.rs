@@ -0,0 +1,109 @@
+use std::time::Instant;
+
+/// Battery and power draw of the runner, a 2S LiPo by default
+#[derive(Copy, Clone, Debug)]
+pub struct BatteryModel {
+    /// Open circuit voltage when full [V]
+    pub full_voltage: f64,
+    /// Open circuit voltage when empty [V]
+    pub empty_voltage: f64,
+    /// [Ah]
+    pub capacity: f64,
+    /// [Ohm]
+    pub internal_resistance: f64,
+    /// Draw of everything but the motors [A]
+    pub idle_current: f64,
+    /// Motor draw per translational speed [A per m/s]
+    pub translational_current: f64,
+    /// Motor draw per rotational speed [A per rad/s]
+    pub rotational_current: f64,
+    /// Motors are switched off below this voltage [V]
+    pub cutoff_voltage: f64,
+}
+
+impl Default for BatteryModel {
+    fn default() -> Self {
+        Self {
+            full_voltage: 8.4,
+            empty_voltage: 6.0,
+            capacity: 0.3,
+            internal_resistance: 0.2,
+            idle_current: 0.15,
+            translational_current: 1.0,
+            rotational_current: 0.15,
+            cutoff_voltage: 6.6,
+        }
+    }
+}
+
+pub struct Battery {
+    model: BatteryModel,
+    // Charge drawn since the battery was full [As]
+    drawn: f64,
+    current: f64,
+    cut_off: bool,
+    last_update: Instant,
+}
+
+impl Battery {
+    pub fn new(model: BatteryModel) -> Self {
+        Self {
+            model,
+            drawn: 0.0,
+            current: model.idle_current,
+            cut_off: false,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Fully charged, with the motors switched back on
+    pub fn charge(&mut self) {
+        *self = Self::new(self.model);
+    }
+
+    /// Drains the battery for the time since the last update with the current motor load, true
+    /// when the voltage has just fallen below the cutoff
+    pub fn update(&mut self, translational: f64, rotational: f64) -> bool {
+        let elapsed = self.last_update.elapsed().as_secs_f64();
+        self.last_update = Instant::now();
+
+        self.current = self.model.idle_current;
+
+        if !self.cut_off {
+            self.current += self.model.translational_current * translational.abs() / 1000.0
+                + self.model.rotational_current * rotational.abs();
+        }
+
+        self.drawn = (self.drawn + self.current * elapsed).min(self.model.capacity * 3600.0);
+
+        if self.cut_off || self.get_voltage() >= self.model.cutoff_voltage {
+            return false;
+        }
+
+        self.cut_off = true;
+
+        true
+    }
+
+    /// Share of the capacity left, from 0 to 1
+    pub fn get_charge(&self) -> f64 {
+        1.0 - self.drawn / (self.model.capacity * 3600.0)
+    }
+
+    /// Voltage under the present load [V]
+    pub fn get_voltage(&self) -> f64 {
+        let open_circuit = self.model.empty_voltage
+            + (self.model.full_voltage - self.model.empty_voltage) * self.get_charge();
+
+        open_circuit - self.current * self.model.internal_resistance
+    }
+
+    /// [A]
+    pub fn get_current(&self) -> f64 {
+        self.current
+    }
+
+    pub fn is_cut_off(&self) -> bool {
+        self.cut_off
+    }
+}
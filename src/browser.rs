@@ -0,0 +1,176 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use pix_engine::prelude::*;
+
+use crate::{mazefile::MazefileFormat, panel::PANEL_X_OFFSET};
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct MazeBrowser {
+    directory: Option<PathBuf>,
+    entries: Vec<PathBuf>,
+    names: Vec<String>,
+    selected: usize,
+    current: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    auto_reload: bool,
+    last_check: Instant,
+    message: Option<String>,
+}
+
+impl MazeBrowser {
+    pub fn new(directory: Option<PathBuf>, current: Option<PathBuf>) -> Self {
+        let mut browser = Self {
+            directory,
+            entries: Vec::new(),
+            names: Vec::new(),
+            selected: 0,
+            modified: current.as_deref().and_then(modified_time),
+            current,
+            auto_reload: true,
+            last_check: Instant::now(),
+            message: None,
+        };
+
+        if let Err(e) = browser.scan() {
+            browser.message = Some(format!("{:#}", e));
+        }
+
+        browser
+    }
+
+    /// Marks `path` as the maze on display, called once it was loaded successfully
+    pub fn set_current(&mut self, path: PathBuf) {
+        self.modified = modified_time(&path);
+        self.current = Some(path);
+        self.message = None;
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    fn scan(&mut self) -> Result<()> {
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+
+        let directory = if directory.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            directory.as_path()
+        };
+
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(directory).context("Couldn't read maze directory")? {
+            let path = entry.context("Couldn't read maze directory")?.path();
+
+            if path.is_file() && MazefileFormat::from_extension(&path).is_some() {
+                entries.push(path);
+            }
+        }
+
+        entries.sort();
+
+        self.names = entries
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into()
+            })
+            .collect();
+
+        self.selected = self
+            .current
+            .as_ref()
+            .and_then(|current| {
+                entries
+                    .iter()
+                    .position(|entry| entry.file_name() == current.file_name())
+            })
+            .unwrap_or(0);
+
+        self.entries = entries;
+
+        Ok(())
+    }
+
+    /// Returns the current mazefile when it changed on disk since it was loaded
+    pub fn poll_changes(&mut self) -> Option<PathBuf> {
+        if !self.auto_reload || self.last_check.elapsed() < RELOAD_CHECK_INTERVAL {
+            return None;
+        }
+
+        self.last_check = Instant::now();
+
+        let current = self.current.clone()?;
+        let modified = modified_time(&current);
+
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+
+            println!("{} changed, reloading", current.display());
+
+            return Some(current);
+        }
+
+        None
+    }
+
+    /// Draws the browser and returns the mazefile the user asked to load
+    pub fn draw(&mut self, s: &mut PixState) -> Result<Option<PathBuf>> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 400;
+
+        let mut load = None;
+
+        s.set_cursor_pos([x_offset, y_offset]);
+
+        if !self.entries.is_empty() {
+            s.next_width(220);
+            s.select_box("##mazes", &mut self.selected, &self.names, 8)?;
+
+            s.same_line(None);
+            if s.button("Load")? {
+                load = Some(self.entries[self.selected].clone());
+            }
+        }
+
+        if s.button("Reload")? {
+            load = self.current.clone();
+        }
+
+        s.same_line(None);
+        if s.button("Rescan")? {
+            if let Err(e) = self.scan() {
+                self.message = Some(format!("{:#}", e));
+            }
+        }
+
+        s.same_line(None);
+        s.checkbox("Auto reload", &mut self.auto_reload)?;
+
+        if let Some(message) = &self.message {
+            s.fill(Color::BLACK);
+            s.stroke(None);
+
+            s.text(message)?;
+        }
+
+        Ok(load)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
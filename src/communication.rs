@@ -1,104 +1,35 @@
 use anyhow::{Context, Result};
-use bitflags::bitflags;
+use maze_simulator_protocol::{MazeRunnerRequest, MazeRunnerResponse};
 use postcard::{from_bytes, to_stdvec};
-use serde::{Deserialize, Serialize};
 use std::io::prelude::*;
 use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
 use std::sync::mpsc::{Receiver, Sender};
 
-use crate::maze::CellState;
-
-const SOCKET: &str = "/tmp/micromouse_simulator_socket";
-
-bitflags! {
-    #[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    #[serde(transparent)]
-    pub struct ButtonsState: u8 {
-        const Reset = 0b00000001;
-        const Button1 = 0b00000010;
-        const Button2 = 0b00000100;
-        const Button3 = 0b00001000;
-        const Button4 = 0b00010000;
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub enum DistanceSensor {
-    FrontLeft,
-    FrontRight,
-    DiagonalLeft,
-    DiagonalRight,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MotionReadout {
-    pub x: i32,
-    pub y: i32,
-    pub theta: f64,
-    pub velocity_translational: f64,
-    pub velocity_rotational: f64,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub enum MazeRunnerRequest {
-    Initialize,
-    MoveForward,
-    RotateRight90,
-    RotateLeft90,
-    GetWallFront,
-    GetWallRight,
-    GetWallLeft,
-    GetButtonsState,
-    UpdateCellState {
-        x: usize,
-        y: usize,
-        state: CellState,
-    },
-    ClearCell {
-        x: usize,
-        y: usize,
-    },
-    UpdateCellValue {
-        x: usize,
-        y: usize,
-        value: i32,
-    },
-    GetDistanceReadout {
-        sensor: DistanceSensor,
-    },
-    GetMotionReadout,
-    SetVelocity {
-        translational: f64,
-        rotational: f64,
-    },
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub enum MazeRunnerResponse {
-    Ack,
-    Error,
-    WallDetected(bool),
-    Buttons(ButtonsState),
-    Distance(u16),
-    Motion(MotionReadout),
+pub enum SimEvent {
+    Request(MazeRunnerRequest),
+    ClientDisconnected,
+    /// Request from the window, nobody waits for its response
+    Panel(MazeRunnerRequest),
 }
 
 pub struct SimCommunication {
     listener: UnixListener,
-    request_tx: Sender<MazeRunnerRequest>,
+    request_tx: Sender<SimEvent>,
     response_rx: Receiver<MazeRunnerResponse>,
 }
 
 impl SimCommunication {
     pub fn new(
-        request_tx: Sender<MazeRunnerRequest>,
+        socket: &Path,
+        request_tx: Sender<SimEvent>,
         response_rx: Receiver<MazeRunnerResponse>,
     ) -> Result<Self> {
-        if std::fs::metadata(SOCKET).is_ok() {
-            std::fs::remove_file(SOCKET).context("Failed to remove existing socket")?;
+        if std::fs::metadata(socket).is_ok() {
+            std::fs::remove_file(socket).context("Failed to remove existing socket")?;
         }
 
-        let listener = UnixListener::bind(SOCKET).context("Failed to create socket")?;
+        let listener = UnixListener::bind(socket).context("Failed to create socket")?;
 
         Ok(Self {
             listener,
@@ -114,7 +45,15 @@ impl SimCommunication {
                 .accept()
                 .context("Failed to accept connection")?;
 
+            println!("Client connected");
+
             self.handle_stream(stream)?;
+
+            println!("Client disconnected");
+
+            self.request_tx
+                .send(SimEvent::ClientDisconnected)
+                .context("Failed to propagate disconnect")?;
         }
     }
 
@@ -122,31 +61,47 @@ impl SimCommunication {
         loop {
             let mut buffer = [0; 100];
 
-            let n = stream
-                .read(&mut buffer[..])
-                .context("Failed to receive request")?;
+            match stream.read(&mut buffer[..]) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => {
+                    println!("Failed to receive request: {e}");
 
-            if n == 0 {
-                return Ok(());
+                    return Ok(());
+                }
             }
 
-            let request: MazeRunnerRequest =
-                from_bytes(&buffer).context("Failed to deserialize request")?;
-
-            self.request_tx
-                .send(request)
-                .context("Failed to propagate request")?;
+            let response = match from_bytes::<MazeRunnerRequest>(&buffer) {
+                Ok(request) => {
+                    self.request_tx
+                        .send(SimEvent::Request(request))
+                        .context("Failed to propagate request")?;
 
-            let response = self.response_rx.recv().context("Failed to get response")?;
+                    self.response_rx.recv().context("Failed to get response")?
+                }
+                Err(e) => {
+                    println!("Failed to deserialize request: {e}");
 
-            let response_buffer: Vec<u8> =
-                to_stdvec(&response).context("Failed to serialize response")?;
+                    MazeRunnerResponse::Error
+                }
+            };
 
-            stream
-                .write_all(response_buffer.as_slice())
-                .context("Failed to send response")?;
+            if let Err(e) = Self::send_response(&mut stream, &response) {
+                println!("{e:#}");
 
-            stream.flush().context("Could not flush the stream: {e}")?;
+                return Ok(());
+            }
         }
     }
+
+    fn send_response(stream: &mut UnixStream, response: &MazeRunnerResponse) -> Result<()> {
+        let response_buffer: Vec<u8> =
+            to_stdvec(response).context("Failed to serialize response")?;
+
+        stream
+            .write_all(response_buffer.as_slice())
+            .context("Failed to send response")?;
+
+        stream.flush().context("Could not flush the stream")
+    }
 }
@@ -2,7 +2,10 @@ use anyhow::Result;
 use pix_engine::{line_, shape::Line};
 use std::{
     marker::PhantomData,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread::sleep,
     time::Duration,
 };
@@ -11,6 +14,7 @@ use crate::{
     engine::Render,
     maze::{Cell, CellState, Maze},
     position::{Angle, Millimeters, Position},
+    shared_maze::SharedMaze,
     simulator::{CELL_SIZE_MM, RATIO_VIS_MM, WALL_WIDTH_MM},
 };
 
@@ -21,7 +25,9 @@ pub trait DistanceSensor {
 }
 
 pub struct DistanceSensorsEnvironment<const R: usize, const C: usize, FL, FR, DL, DR> {
+    shared_maze: SharedMaze<R, C>,
     maze: Maze<R, C>,
+    revision: u64,
     runner_position: Arc<Mutex<Position<R>>>,
     distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
     phantom_fl: PhantomData<FL>,
@@ -39,12 +45,14 @@ where
     DR: DistanceSensor,
 {
     pub fn new(
-        maze: Maze<R, C>,
+        maze: SharedMaze<R, C>,
         runner_position: Arc<Mutex<Position<R>>>,
         distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
     ) -> Self {
         Self {
-            maze,
+            revision: maze.get_revision(),
+            shared_maze: maze.clone(),
+            maze: maze.get_maze(),
             runner_position,
             distance_sensors,
             phantom_fl: PhantomData,
@@ -54,8 +62,17 @@ where
         }
     }
 
-    pub fn process(self) -> Result<()> {
-        loop {
+    /// Updates the readings until `stop` is set
+    pub fn process(mut self, stop: Arc<AtomicBool>) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            // Beams are traced against a copy that is refreshed whenever the maze changes
+            let revision = self.shared_maze.get_revision();
+
+            if revision != self.revision {
+                self.maze = self.shared_maze.get_maze();
+                self.revision = revision;
+            }
+
             {
                 let runner_position = self.runner_position.lock().unwrap().clone();
                 let mut distance_sensors = self.distance_sensors.lock().unwrap();
@@ -75,6 +92,8 @@ where
 
             sleep(Duration::from_millis(20));
         }
+
+        Ok(())
     }
 
     pub fn is_wall_at(
@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use pix_engine::prelude::*;
+
+use crate::{
+    maze::{Cell, CellState, Maze},
+    mazefile::{Mazefile, MazefileFormat},
+    panel::PANEL_X_OFFSET,
+    shared_maze::SharedMaze,
+    simulator::{CELL_SIZE_VIS, WALL_WIDTH_VIS},
+    validation::{MazeValidator, Severity},
+};
+
+// How far from the middle of a wall a click still toggles it
+const WALL_CLICK_TOLERANCE_VIS: i32 = CELL_SIZE_VIS / 4;
+
+pub struct MazeEditor<const R: usize, const C: usize> {
+    enabled: bool,
+    mazefile: Option<PathBuf>,
+    messages: Vec<String>,
+}
+
+impl<const R: usize, const C: usize> MazeEditor<R, C> {
+    pub fn new(mazefile: Option<PathBuf>) -> Self {
+        Self {
+            enabled: false,
+            mazefile,
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn set_mazefile(&mut self, mazefile: PathBuf) {
+        self.mazefile = Some(mazefile);
+        self.messages.clear();
+    }
+
+    /// Left click toggles the nearest wall or places the start cell, right click toggles a goal cell
+    pub fn click(&mut self, maze: &SharedMaze<R, C>, button: Mouse, position: Point<i32>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        let (px, py) = (position.x(), position.y());
+
+        let maze_width = CELL_SIZE_VIS * C as i32 + WALL_WIDTH_VIS;
+        let maze_height = CELL_SIZE_VIS * R as i32 + WALL_WIDTH_VIS;
+
+        if px < 0 || py < 0 || px >= maze_width || py >= maze_height {
+            return false;
+        }
+
+        let column = (px / CELL_SIZE_VIS).min(C as i32 - 1);
+        let row = (py / CELL_SIZE_VIS).min(R as i32 - 1);
+
+        let cell = Cell {
+            x: column as usize,
+            y: R - 1 - row as usize,
+        };
+
+        // Offsets from the middle of the west and north walls of the cell
+        let dx = px - column * CELL_SIZE_VIS - WALL_WIDTH_VIS / 2;
+        let dy = py - row * CELL_SIZE_VIS - WALL_WIDTH_VIS / 2;
+
+        let walls = [
+            (CellState::WestWall, dx),
+            (CellState::EastWall, CELL_SIZE_VIS - dx),
+            (CellState::NorthWall, dy),
+            (CellState::SouthWall, CELL_SIZE_VIS - dy),
+        ];
+
+        let (wall, distance) = walls
+            .into_iter()
+            .min_by_key(|(_, distance)| distance.abs())
+            .expect("Walls shouldn't be empty");
+
+        match button {
+            Mouse::Left if distance.abs() <= WALL_CLICK_TOLERANCE_VIS => maze.update(|maze| {
+                let value = !maze.get_cell_state(cell).contains(wall);
+
+                maze.update_cell_state(cell, wall, value);
+            }),
+            Mouse::Left => maze.update(|maze| maze.set_start_cell(cell)),
+            Mouse::Right => maze.update(|maze| {
+                if maze.is_goal_cell(cell) {
+                    maze.clear_goal_cell(cell);
+                } else {
+                    maze.set_goal_cell(cell);
+                }
+            }),
+            _ => return false,
+        }
+
+        self.messages.clear();
+
+        true
+    }
+
+    /// Validates and saves the maze, returning the path of the file when it was written
+    pub fn save(&mut self, maze: &Maze<R, C>) -> Option<PathBuf> {
+        let Some(path) = self.mazefile.clone() else {
+            self.messages = vec!["No mazefile to save to".to_string()];
+
+            return None;
+        };
+
+        let diagnostics = MazeValidator::new(maze).validate();
+
+        self.messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            self.messages.insert(0, "Not saved:".to_string());
+
+            return None;
+        }
+
+        let format = MazefileFormat::from_extension(&path).unwrap_or(MazefileFormat::Ascii);
+
+        match Mazefile::from_maze(maze, format).and_then(|mazefile| mazefile.save(path.clone())) {
+            Ok(()) => {
+                println!("Saved maze to {}", path.display());
+
+                self.messages
+                    .insert(0, format!("Saved to {}", path.display()));
+
+                if !format.stores_markers() {
+                    self.messages
+                        .push(format!("{:?} format doesn't store start and goal", format));
+                }
+
+                Some(path)
+            }
+            Err(error) => {
+                self.messages.insert(0, format!("Not saved: {}", error));
+
+                None
+            }
+        }
+    }
+
+    pub fn draw_controls(
+        &mut self,
+        s: &mut PixState,
+        maze: &SharedMaze<R, C>,
+    ) -> Result<Option<PathBuf>> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 480;
+        let y_padding = 20;
+
+        s.set_cursor_pos([x_offset, y_offset]);
+
+        s.checkbox("Edit maze", &mut self.enabled)?;
+
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let mut saved = None;
+
+        s.same_line(None);
+        if s.button("Save")? {
+            saved = self.save(&maze.read());
+        }
+
+        s.fill(Color::BLACK);
+        s.stroke(None);
+
+        let help = [
+            "Left click: toggle wall / set start",
+            "Right click: toggle goal cell",
+        ];
+
+        let lines = help
+            .iter()
+            .map(|line| line.to_string())
+            .chain(self.messages.iter().cloned());
+
+        for (i, line) in lines.enumerate() {
+            s.set_cursor_pos([x_offset, y_offset + 40 + y_padding * i as i32]);
+
+            s.text(line)?;
+        }
+
+        Ok(saved)
+    }
+
+    pub fn draw_markers(&self, s: &mut PixState, maze: &Maze<R, C>) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        s.stroke(None);
+
+        let start = maze.get_start_cell();
+
+        for (cells, color) in [
+            (maze.get_goal_cells(), Color::rgb(0x30, 0x30, 0x10)),
+            (vec![start], Color::rgb(0x10, 0x30, 0x10)),
+        ] {
+            s.fill(color);
+
+            for cell in cells {
+                s.rect(rect![
+                    cell.x as i32 * CELL_SIZE_VIS + WALL_WIDTH_VIS + 1,
+                    (R - cell.y - 1) as i32 * CELL_SIZE_VIS + WALL_WIDTH_VIS + 1,
+                    CELL_SIZE_VIS - WALL_WIDTH_VIS - 2,
+                    CELL_SIZE_VIS - WALL_WIDTH_VIS - 2,
+                ])?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,21 @@
 use anyhow::Result;
 use pix_engine::prelude::*;
-use std::sync::{Arc, Mutex};
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc, Mutex},
+};
 
 use crate::{
-    communication::ButtonsState, distance_sensors::DistanceSensorsReading, maze::Posts,
+    browser::MazeBrowser,
+    communication::SimEvent,
+    distance_sensors::DistanceSensorsReading,
+    editor::MazeEditor,
+    maze::{Cell, GoalArea, Posts},
+    mazefile::Mazefile,
+    overlay::PathOverlay,
     panel::SimPanel,
+    placement::RunnerPlacement,
+    shared_maze::SharedMaze,
 };
 
 pub trait Render {
@@ -13,43 +24,95 @@ pub trait Render {
         C: Into<Option<Color>> + std::marker::Copy;
 }
 
-pub struct SimEngine<const R: usize, const C: usize, T, S, U> {
+pub struct SimEngine<const R: usize, const C: usize, T, U> {
     posts: Posts<R, C>,
-    maze: S,
+    maze: SharedMaze<R, C>,
+    editor: MazeEditor<R, C>,
+    browser: MazeBrowser,
+    overlay: PathOverlay<R, C>,
+    placement: Option<RunnerPlacement<R, C>>,
+    start: Option<Cell<R, C>>,
+    goal: Option<GoalArea>,
     runner_position: Arc<Mutex<U>>,
-    panel: SimPanel,
+    panel: SimPanel<R, C>,
     runner_context: Arc<Mutex<T>>,
     distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
 }
 
-impl<const R: usize, const C: usize, T, S, U> SimEngine<R, C, T, S, U>
+impl<const R: usize, const C: usize, T, U> SimEngine<R, C, T, U>
 where
     T: Render,
-    S: Render,
     U: Render,
 {
     pub fn new(
-        maze: S,
+        maze: SharedMaze<R, C>,
         runner_position: Arc<Mutex<U>>,
-        buttons: Arc<Mutex<ButtonsState>>,
+        panel: SimPanel<R, C>,
         runner_context: Arc<Mutex<T>>,
         distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
     ) -> Self {
         Self {
             maze,
+            editor: MazeEditor::new(None),
+            browser: MazeBrowser::new(None, None),
+            overlay: PathOverlay::new(),
+            placement: None,
+            start: None,
+            goal: None,
             posts: Posts {},
             runner_position,
-            panel: SimPanel::new(buttons, distance_sensors.clone()),
+            panel,
             runner_context,
             distance_sensors,
         }
     }
+
+    pub fn mazefile(mut self, mazefile: Option<PathBuf>, maze_directory: Option<PathBuf>) -> Self {
+        self.editor = MazeEditor::new(mazefile.clone());
+        self.browser = MazeBrowser::new(maze_directory, mazefile);
+        self
+    }
+
+    /// Start and goal overriding the markers of every mazefile loaded in the window
+    pub fn markers(mut self, start: Option<Cell<R, C>>, goal: Option<GoalArea>) -> Self {
+        self.start = start;
+        self.goal = goal;
+        self
+    }
+
+    /// Lets clicks on the maze place the runner, sent to the environment as events
+    pub fn events(mut self, events: Sender<SimEvent>) -> Self {
+        self.placement = Some(RunnerPlacement::new(events));
+        self
+    }
+
+    fn load_maze(&mut self, path: PathBuf) -> Result<()> {
+        let mut maze = Mazefile::<R, C>::load(path.clone())?.parse()?;
+
+        if let Some(start) = self.start {
+            maze.set_start_cell(start);
+        }
+
+        if let Some(goal) = self.goal {
+            maze.set_goal_area(goal)?;
+        }
+
+        maze.infer_missing_markers(GoalArea::center::<R, C>())?;
+
+        self.maze.replace(maze);
+
+        println!("Loaded maze from {}", path.display());
+
+        self.editor.set_mazefile(path.clone());
+        self.browser.set_current(path);
+
+        Ok(())
+    }
 }
 
-impl<const R: usize, const C: usize, T, S, U> PixEngine for SimEngine<R, C, T, S, U>
+impl<const R: usize, const C: usize, T, U> PixEngine for SimEngine<R, C, T, U>
 where
     T: Render,
-    S: Render,
     U: Render,
 {
     fn on_start(&mut self, s: &mut PixState) -> PixResult<()> {
@@ -59,16 +122,29 @@ where
     }
 
     fn on_update(&mut self, s: &mut PixState) -> PixResult<()> {
+        if let Some(path) = self.browser.poll_changes() {
+            if let Err(e) = self.load_maze(path) {
+                self.browser.set_message(format!("{:#}", e));
+            }
+        }
+
         s.clear()?;
 
         self.posts.draw(s, Color::DIM_GRAY, Color::DARK_GRAY)?;
-        self.maze.draw(s, Color::DIM_GRAY, Color::DARK_GRAY)?;
+        {
+            let maze = self.maze.read();
+
+            self.editor.draw_markers(s, &maze)?;
+            maze.draw(s, Color::DIM_GRAY, Color::DARK_GRAY)?;
+        }
 
         self.runner_context
             .lock()
             .unwrap()
             .draw(s, Color::RED, Color::DARK_GRAY)?;
 
+        self.overlay.draw_path(s, &self.maze)?;
+
         self.runner_position
             .lock()
             .unwrap()
@@ -80,10 +156,38 @@ where
             .draw(s, Color::ORANGE_RED, Color::ORANGE_RED)?;
 
         self.panel.draw(s, Color::DIM_GRAY, Color::DARK_GRAY)?;
+        self.overlay.draw_controls(s)?;
+        if let Some(placement) = &mut self.placement {
+            placement.draw_controls(s)?;
+        }
+
+        if let Some(path) = self.browser.draw(s)? {
+            if let Err(e) = self.load_maze(path) {
+                self.browser.set_message(format!("{:#}", e));
+            }
+        }
+
+        if let Some(path) = self.editor.draw_controls(s, &self.maze)? {
+            // The maze on display already matches the saved file
+            self.browser.set_current(path);
+        }
 
         Ok(())
     }
 
+    fn on_mouse_pressed(
+        &mut self,
+        _s: &mut PixState,
+        button: Mouse,
+        position: Point<i32>,
+    ) -> PixResult<bool> {
+        Ok(self.editor.click(&self.maze, button, position)
+            || self
+                .placement
+                .as_mut()
+                .is_some_and(|placement| placement.click(button, position)))
+    }
+
     fn on_stop(&mut self, _s: &mut PixState) -> PixResult<()> {
         Ok(())
     }
@@ -1,48 +1,163 @@
 use anyhow::{anyhow, Context, Result};
+use maze_simulator_protocol::{
+    BatteryReadout, ButtonsState, DistanceSensor, Heading, LedColor, MazeRunnerRequest,
+    MazeRunnerResponse, MotionProfile, MotionReadout, MoveStatus, TurnDirection, TurnKind,
+    DISPLAY_COLUMNS,
+};
 use std::{
+    collections::VecDeque,
     sync::{
-        mpsc::{Receiver, Sender, TryRecvError},
+        mpsc::{Receiver, RecvTimeoutError, Sender},
         Arc, Mutex,
     },
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    communication::{
-        ButtonsState, DistanceSensor, MazeRunnerRequest, MazeRunnerResponse, MotionReadout,
-    },
+    battery::{Battery, BatteryModel},
+    communication::SimEvent,
     context::RunnerContext,
     distance_sensors::DistanceSensorsReading,
     maze::{Cell, CellState, Maze},
+    outputs::{RunnerOutputs, DEFAULT_LED_COUNT},
     position::{Angle, Position},
+    run::{RunEvent, RunLog},
     runner::{MazerRunner, RotationDirection, SensorDirection},
+    shared_maze::{MazeChange, SharedMaze},
+    trajectory::Segment,
     velocity::Velocity,
 };
 
 const TRANSLATIONAL_VELOCITY: f64 = 400.0; // 400.0 [mm/s]
 const ROTATIONAL_VELOCITY: f64 = 6.98131701; // ~400 [deg/s]
 
+// Constant speed without a ramp, as moves ran before profiles
+const TRANSLATIONAL_PROFILE: MotionProfile = MotionProfile {
+    max_speed: TRANSLATIONAL_VELOCITY,
+    acceleration: f64::INFINITY,
+    end_speed: 0.0,
+};
+
+const ROTATIONAL_PROFILE: MotionProfile = MotionProfile {
+    max_speed: ROTATIONAL_VELOCITY,
+    acceleration: f64::INFINITY,
+    end_speed: 0.0,
+};
+
+// Finished moves remembered for status requests and the completion queue
+const FINISHED_MOVES: usize = 64;
+
+enum MoveCommand {
+    /// Half cells, or wall midpoints on a diagonal, `None` takes either heading
+    Straight {
+        steps: usize,
+        diagonal: Option<bool>,
+        profile: MotionProfile,
+    },
+    Turn {
+        kind: TurnKind,
+        direction: TurnDirection,
+    },
+    Rotate {
+        direction: RotationDirection,
+        profile: MotionProfile,
+    },
+}
+
+enum Stage<const R: usize, const C: usize> {
+    /// Straight to the target, taking on the runner states it passes on the way
+    Straight {
+        target: Position<R>,
+        profile: MotionProfile,
+        checkpoints: VecDeque<MazerRunner<R, C>>,
+    },
+    Arc {
+        target: Position<R>,
+        rotational: f64,
+    },
+    Rotate {
+        target: Position<R>,
+        sign: f64,
+        profile: MotionProfile,
+    },
+}
+
+struct ActiveMove<const R: usize, const C: usize> {
+    id: u32,
+    stages: VecDeque<Stage<R, C>>,
+    stage_count: usize,
+    // Distance or angle left when the current stage started
+    stage_length: Option<f64>,
+    stage_progress: f64,
+    // Runner state once the last stage is done, straights take on theirs along the way
+    end_state: Option<MazerRunner<R, C>>,
+    outcome: MoveStatus,
+    coast: bool,
+    last_update: Instant,
+}
+
+impl<const R: usize, const C: usize> ActiveMove<R, C> {
+    fn new(id: u32) -> Self {
+        Self {
+            id,
+            stages: VecDeque::new(),
+            stage_count: 0,
+            stage_length: None,
+            stage_progress: 0.0,
+            end_state: None,
+            outcome: MoveStatus::Done,
+            coast: false,
+            last_update: Instant::now(),
+        }
+    }
+
+    fn push(&mut self, stage: Stage<R, C>) {
+        self.stages.push_back(stage);
+        self.stage_count += 1;
+    }
+
+    fn get_progress(&self) -> f64 {
+        if self.stage_count == 0 {
+            return 1.0;
+        }
+
+        let done = self.stage_count - self.stages.len();
+
+        (done as f64 + self.stage_progress) / self.stage_count as f64
+    }
+}
+
 pub struct SimEnvironment<const R: usize, const C: usize> {
-    maze: Maze<R, C>,
-    request_rx: Receiver<MazeRunnerRequest>,
-    response_tx: Sender<MazeRunnerResponse>,
+    maze: SharedMaze<R, C>,
+    maze_changes: Receiver<MazeChange>,
     runner_position: Arc<Mutex<Position<R>>>,
     runner: MazerRunner<R, C>,
     buttons: Arc<Mutex<ButtonsState>>,
+    outputs: Arc<Mutex<RunnerOutputs>>,
+    battery: Arc<Mutex<Battery>>,
     runner_context: Arc<Mutex<RunnerContext<R, C>>>,
     distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
     velocity: Arc<Mutex<Velocity>>,
+    run_log: Arc<Mutex<RunLog<R, C>>>,
+    reset_on_crash: bool,
+    // Where a continuous move stops when no further move arrives
+    coasting: Option<MazerRunner<R, C>>,
+    queued_moves: VecDeque<(u32, MoveCommand)>,
+    active_move: Option<ActiveMove<R, C>>,
+    finished_moves: VecDeque<(u32, MoveStatus)>,
+    completed_moves: VecDeque<(u32, MoveStatus)>,
+    next_move_id: u32,
 }
 
 impl<const R: usize, const C: usize> SimEnvironment<R, C> {
-    pub fn new(
-        maze: Maze<R, C>,
-        request_rx: Receiver<MazeRunnerRequest>,
-        response_tx: Sender<MazeRunnerResponse>,
-    ) -> Result<Self> {
+    pub fn new(maze: Maze<R, C>) -> Result<Self> {
         let runner = MazerRunner::new(&maze)?;
 
+        let maze = SharedMaze::new(maze);
+
+        let maze_changes = maze.subscribe();
+
         let runner_position = Position::new(270.0, 270.0, Angle::degrees(0.0));
 
         let runner_position = Arc::new(Mutex::new(runner_position));
@@ -51,28 +166,86 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
 
         let buttons = Arc::new(Mutex::new(ButtonsState::default()));
 
+        let outputs = Arc::new(Mutex::new(RunnerOutputs::new(DEFAULT_LED_COUNT)));
+
+        let battery = Arc::new(Mutex::new(Battery::new(BatteryModel::default())));
+
         let runner_context = Arc::new(Mutex::new(RunnerContext::new()));
 
         let velocity = Arc::new(Mutex::new(Velocity::new()));
 
+        let run_log = Arc::new(Mutex::new(RunLog::new()));
+
         Ok(Self {
             maze,
-            request_rx,
-            response_tx,
+            maze_changes,
             runner_position,
             runner,
             buttons,
+            outputs,
+            battery,
             runner_context,
             distance_sensors,
             velocity,
+            run_log,
+            reset_on_crash: false,
+            coasting: None,
+            queued_moves: VecDeque::new(),
+            active_move: None,
+            finished_moves: VecDeque::new(),
+            completed_moves: VecDeque::new(),
+            next_move_id: 1,
         })
     }
 
-    pub fn process(mut self) -> Result<()> {
+    pub fn set_reset_on_crash(&mut self, reset_on_crash: bool) {
+        self.reset_on_crash = reset_on_crash;
+    }
+
+    pub fn set_led_count(&mut self, led_count: usize) {
+        *self.outputs.lock().unwrap() = RunnerOutputs::new(led_count);
+    }
+
+    pub fn set_battery_model(&mut self, model: BatteryModel) {
+        *self.battery.lock().unwrap() = Battery::new(model);
+    }
+
+    pub fn process(
+        mut self,
+        request_rx: Receiver<SimEvent>,
+        response_tx: Sender<MazeRunnerResponse>,
+    ) -> Result<()> {
         loop {
-            match self.request_rx.try_recv() {
-                Ok(request) => self.process_request(request)?,
-                Err(TryRecvError::Empty) => {}
+            // Edits keep the run going, the runner only restarts in a different maze
+            let loaded = self
+                .maze_changes
+                .try_iter()
+                .filter(|change| *change == MazeChange::Loaded)
+                .count();
+
+            if loaded > 0 {
+                if let Err(e) = self.process_maze_loaded() {
+                    println!("{e:#}");
+                }
+            }
+
+            if let Err(e) = self.process_moves() {
+                println!("{e:#}");
+            }
+
+            self.process_coasting();
+            self.process_battery();
+
+            // Waiting for the next request paces the loop with the velocity integrator
+            match request_rx.recv_timeout(Duration::from_micros(100)) {
+                Ok(SimEvent::Request(request)) => response_tx
+                    .send(self.respond(request))
+                    .context("Failed to propagate response")?,
+                Ok(SimEvent::ClientDisconnected) => self.process_disconnect(),
+                Ok(SimEvent::Panel(request)) => {
+                    self.respond(request);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
                 Err(e) => return Err(anyhow!("Channel dropped: {e}")),
             };
         }
@@ -86,6 +259,14 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
         self.buttons.clone()
     }
 
+    pub fn get_outputs_handle(&self) -> Arc<Mutex<RunnerOutputs>> {
+        self.outputs.clone()
+    }
+
+    pub fn get_battery_handle(&self) -> Arc<Mutex<Battery>> {
+        self.battery.clone()
+    }
+
     pub fn get_runner_context_handle(&self) -> Arc<Mutex<RunnerContext<R, C>>> {
         self.runner_context.clone()
     }
@@ -98,26 +279,85 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
         self.velocity.clone()
     }
 
-    fn process_request(&mut self, request: MazeRunnerRequest) -> Result<()> {
-        println!("{:?}", request);
+    pub fn get_run_log_handle(&self) -> Arc<Mutex<RunLog<R, C>>> {
+        self.run_log.clone()
+    }
+
+    pub fn get_maze_handle(&self) -> SharedMaze<R, C> {
+        self.maze.clone()
+    }
+
+    pub fn get_runner(&self) -> &MazerRunner<R, C> {
+        &self.runner
+    }
 
+    /// A failing request is answered with an error, the environment keeps serving
+    fn respond(&mut self, request: MazeRunnerRequest) -> MazeRunnerResponse {
+        self.process_request(request).unwrap_or_else(|e| {
+            println!("{e:#}");
+
+            MazeRunnerResponse::Error
+        })
+    }
+
+    pub fn process_request(&mut self, request: MazeRunnerRequest) -> Result<MazeRunnerResponse> {
         let response = match request {
             MazeRunnerRequest::Initialize => self.process_initialize()?,
             MazeRunnerRequest::GetWallFront => MazeRunnerResponse::WallDetected(
                 self.runner
-                    .is_wall_detected(&self.maze, SensorDirection::Front),
+                    .is_wall_detected(&self.maze.read(), SensorDirection::Front),
             ),
             MazeRunnerRequest::GetWallLeft => MazeRunnerResponse::WallDetected(
                 self.runner
-                    .is_wall_detected(&self.maze, SensorDirection::Left),
+                    .is_wall_detected(&self.maze.read(), SensorDirection::Left),
             ),
             MazeRunnerRequest::GetWallRight => MazeRunnerResponse::WallDetected(
                 self.runner
-                    .is_wall_detected(&self.maze, SensorDirection::Right),
+                    .is_wall_detected(&self.maze.read(), SensorDirection::Right),
             ),
-            MazeRunnerRequest::MoveForward => self.process_move_forward(),
-            MazeRunnerRequest::RotateLeft90 => self.process_rotate(RotationDirection::Left),
-            MazeRunnerRequest::RotateRight90 => self.process_rotate(RotationDirection::Right),
+            MazeRunnerRequest::MoveForward => {
+                self.process_blocking_move(straight(2, Some(false), TRANSLATIONAL_PROFILE))?
+            }
+            MazeRunnerRequest::MoveForwardCells { cells } => self.process_blocking_move(
+                straight(2 * cells as usize, Some(false), TRANSLATIONAL_PROFILE),
+            )?,
+            MazeRunnerRequest::MoveHalfCell => {
+                self.process_blocking_move(straight(1, Some(false), TRANSLATIONAL_PROFILE))?
+            }
+            MazeRunnerRequest::MoveForwardContinuous { half_cells } => {
+                let profile = MotionProfile {
+                    end_speed: TRANSLATIONAL_VELOCITY,
+                    ..TRANSLATIONAL_PROFILE
+                };
+
+                self.process_blocking_move(straight(half_cells as usize, Some(false), profile))?
+            }
+            MazeRunnerRequest::MoveDiagonal { steps } => self.process_blocking_move(straight(
+                steps as usize,
+                Some(true),
+                TRANSLATIONAL_PROFILE,
+            ))?,
+            MazeRunnerRequest::MoveProfiled { steps, profile } => {
+                self.process_blocking_move(straight(steps as usize, None, profile))?
+            }
+            MazeRunnerRequest::Rotate90Profiled { direction, profile } => self
+                .process_blocking_move(MoveCommand::Rotate {
+                    direction: direction.into(),
+                    profile,
+                })?,
+            MazeRunnerRequest::Turn { kind, direction } => {
+                self.process_blocking_move(MoveCommand::Turn { kind, direction })?
+            }
+            MazeRunnerRequest::RotateLeft90 => self.process_blocking_move(MoveCommand::Rotate {
+                direction: RotationDirection::Left,
+                profile: ROTATIONAL_PROFILE,
+            })?,
+            MazeRunnerRequest::RotateRight90 => {
+                self.process_blocking_move(MoveCommand::Rotate {
+                    direction: RotationDirection::Right,
+                    profile: ROTATIONAL_PROFILE,
+                })?
+            }
             MazeRunnerRequest::GetButtonsState => self.process_buttons(),
             MazeRunnerRequest::UpdateCellState { x, y, state } => {
                 self.process_update_cell_state(x, y, state)
@@ -135,95 +375,755 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
                 translational,
                 rotational,
             } => self.process_set_velocity(translational, rotational),
+            MazeRunnerRequest::GetGoalCell { index } => self.process_goal_cell(index),
+            MazeRunnerRequest::StartMove { steps, profile } => MazeRunnerResponse::MoveStarted(
+                self.queue_move(straight(steps as usize, None, profile)),
+            ),
+            MazeRunnerRequest::StartTurn { kind, direction } => MazeRunnerResponse::MoveStarted(
+                self.queue_move(MoveCommand::Turn { kind, direction }),
+            ),
+            MazeRunnerRequest::StartRotate90 { direction, profile } => {
+                MazeRunnerResponse::MoveStarted(self.queue_move(MoveCommand::Rotate {
+                    direction: direction.into(),
+                    profile,
+                }))
+            }
+            MazeRunnerRequest::GetMoveStatus { id } => {
+                MazeRunnerResponse::MoveStatus(self.get_move_status(id))
+            }
+            MazeRunnerRequest::CancelMove { id } => self.process_cancel_move(id),
+            MazeRunnerRequest::WaitMove { id } => {
+                MazeRunnerResponse::MoveStatus(self.wait_for_move(id)?)
+            }
+            MazeRunnerRequest::PollMoveCompletion => {
+                MazeRunnerResponse::MoveCompleted(self.completed_moves.pop_front())
+            }
+            MazeRunnerRequest::PlaceRunner { x, y, heading } => {
+                self.process_place_runner(x, y, heading)
+            }
+            MazeRunnerRequest::SetPose { x, y, theta } => self.process_set_pose(x, y, theta),
+            MazeRunnerRequest::SetLed { index, color } => self.process_set_led(index, color),
+            MazeRunnerRequest::Beep {
+                frequency,
+                duration,
+            } => self.process_beep(frequency, duration),
+            MazeRunnerRequest::SetDisplayLine { line, text } => {
+                self.process_set_display_line(line, text)
+            }
+            MazeRunnerRequest::ClearDisplay => self.process_clear_display(),
+            MazeRunnerRequest::GetBatteryReadout => self.process_battery_readout(),
         };
 
-        self.response_tx
-            .send(response)
-            .context("Failed to propagate response")
+        Ok(response)
+    }
+
+    fn process_disconnect(&mut self) {
+        self.cancel_moves();
+        self.completed_moves.clear();
+
+        self.outputs.lock().unwrap().reset();
+
+        println!("Runner stopped, waiting for a new client");
+    }
+
+    fn process_maze_loaded(&mut self) -> Result<()> {
+        self.reset_runner()?;
+
+        self.battery.lock().unwrap().charge();
+
+        *self.runner_context.lock().unwrap() = RunnerContext::new();
+
+        self.run_log.lock().unwrap().reset();
+
+        println!("Maze loaded, runner reset to the start cell");
+
+        Ok(())
     }
 
     fn process_initialize(&mut self) -> Result<MazeRunnerResponse> {
-        self.runner = MazerRunner::new(&self.maze)?;
+        self.reset_runner()?;
+
+        // The idle draw alone empties the battery in a couple of hours, so every client
+        // starts on a full one
+        self.battery.lock().unwrap().charge();
+
+        self.run_log.lock().unwrap().abort_run();
+
+        Ok(MazeRunnerResponse::Ack)
+    }
+
+    fn process_place_runner(&mut self, x: usize, y: usize, heading: Heading) -> MazeRunnerResponse {
+        let cell = match Cell::new(x, y) {
+            Ok(cell) => cell,
+            Err(_) => return MazeRunnerResponse::Error,
+        };
+
+        match MazerRunner::in_cell(cell, heading.into()) {
+            Ok(runner) => self.place_runner(runner),
+            Err(e) => {
+                println!("{e}");
+
+                MazeRunnerResponse::Error
+            }
+        }
+    }
+
+    fn process_set_pose(&mut self, x: f64, y: f64, theta: f64) -> MazeRunnerResponse {
+        let position = Position::new(x, y, Angle::degrees(theta));
+
+        match MazerRunner::nearest(&position) {
+            Ok(runner) => self.place_runner(runner),
+            Err(e) => {
+                println!("{e}");
+
+                MazeRunnerResponse::Error
+            }
+        }
+    }
+
+    /// Stops the runner and puts it down elsewhere, the current run can't go on from there
+    fn place_runner(&mut self, runner: MazerRunner<R, C>) -> MazeRunnerResponse {
+        self.cancel_moves();
+
+        self.runner = runner;
+
+        // Moves and turns are planned from the discrete pose, so the runner starts exactly there
+        let position = self.runner.get_real_position();
+        let cell = self.runner.get_cell();
+
+        println!(
+            "Runner placed at ({:.0}, {:.0}) mm in ({}, {}) facing {:?}",
+            position.x,
+            position.y,
+            cell.x,
+            cell.y,
+            self.runner.get_orientation()
+        );
+
+        *self.runner_position.lock().unwrap() = position;
+
+        self.run_log.lock().unwrap().abort_run();
+
+        MazeRunnerResponse::Ack
+    }
+
+    fn reset_runner(&mut self) -> Result<()> {
+        self.runner = MazerRunner::new(&self.maze.read())?;
+        self.cancel_moves();
 
         let mut runner_position = self.runner_position.lock().unwrap();
 
         *runner_position = self.runner.get_real_position();
 
-        Ok(MazeRunnerResponse::Ack)
+        Ok(())
     }
 
-    fn process_move_forward(&mut self) -> MazeRunnerResponse {
-        if self.runner.move_forward(&self.maze).is_err() {
-            return MazeRunnerResponse::Error;
+    fn process_crash(&mut self) -> Result<()> {
+        let cell = self.runner.get_cell();
+        let orientation = self.runner.get_orientation();
+
+        println!(
+            "Runner crashed at ({}, {}) facing {:?}",
+            cell.x, cell.y, orientation
+        );
+
+        self.run_log
+            .lock()
+            .unwrap()
+            .record(RunEvent::Crash { cell, orientation });
+
+        if self.reset_on_crash {
+            self.reset_runner()?;
+
+            self.run_log.lock().unwrap().abort_run();
         }
 
-        let next_position = self.runner.get_real_position();
+        Ok(())
+    }
 
-        {
-            let mut velocity = self.velocity.lock().unwrap();
+    fn queue_move(&mut self, command: MoveCommand) -> u32 {
+        let id = self.next_move_id;
 
-            velocity.translational = TRANSLATIONAL_VELOCITY;
-            velocity.rotational = 0.0;
+        self.next_move_id = self.next_move_id.wrapping_add(1);
+        self.queued_moves.push_back((id, command));
+
+        id
+    }
+
+    /// Queues the move and waits for it, the way moves ran before they could be started
+    fn process_blocking_move(&mut self, command: MoveCommand) -> Result<MazeRunnerResponse> {
+        let id = self.queue_move(command);
+
+        match self.wait_for_move(id)? {
+            MoveStatus::Done => Ok(MazeRunnerResponse::Ack),
+            _ => Ok(MazeRunnerResponse::Error),
         }
+    }
 
+    fn wait_for_move(&mut self, id: u32) -> Result<MoveStatus> {
         loop {
+            match self.get_move_status(id) {
+                MoveStatus::Queued | MoveStatus::Running(_) => {}
+                status => return Ok(status),
+            }
+
             sleep(Duration::from_micros(100));
 
-            let mut runner_position = self.runner_position.lock().unwrap();
+            self.process_moves()?;
+            self.process_battery();
+        }
+    }
 
-            if (runner_position.x - next_position.x).abs() < 2.0
-                && (runner_position.y - next_position.y).abs() < 2.0
-            {
-                runner_position.x = next_position.x;
-                runner_position.y = next_position.y;
+    fn get_move_status(&self, id: u32) -> MoveStatus {
+        if let Some(active) = self.active_move.as_ref().filter(|active| active.id == id) {
+            return MoveStatus::Running(active.get_progress() as f32);
+        }
 
-                let mut velocity = self.velocity.lock().unwrap();
+        if self.queued_moves.iter().any(|(queued, _)| *queued == id) {
+            return MoveStatus::Queued;
+        }
 
-                velocity.translational = 0.0;
+        self.finished_moves
+            .iter()
+            .find(|(finished, _)| *finished == id)
+            .map_or(MoveStatus::Unknown, |(_, status)| *status)
+    }
 
-                break;
+    fn record_move(&mut self, id: u32, status: MoveStatus) {
+        for moves in [&mut self.finished_moves, &mut self.completed_moves] {
+            if moves.len() == FINISHED_MOVES {
+                moves.pop_front();
             }
+
+            moves.push_back((id, status));
+        }
+    }
+
+    fn process_cancel_move(&mut self, id: u32) -> MazeRunnerResponse {
+        if let Some(index) = self
+            .queued_moves
+            .iter()
+            .position(|(queued, _)| *queued == id)
+        {
+            self.queued_moves.remove(index);
+            self.record_move(id, MoveStatus::Cancelled);
+
+            return MazeRunnerResponse::Ack;
         }
 
+        let Some(active) = self.active_move.as_mut().filter(|active| active.id == id) else {
+            return MazeRunnerResponse::Error;
+        };
+
+        // Turns and rotations have nowhere to stop halfway, only plain straights brake early
+        if active.end_state.is_some() || active.stages.len() != 1 {
+            return MazeRunnerResponse::Error;
+        }
+
+        let Some(Stage::Straight {
+            target,
+            profile,
+            checkpoints,
+        }) = active.stages.front_mut()
+        else {
+            return MazeRunnerResponse::Error;
+        };
+
+        if let Some(next) = checkpoints.pop_front() {
+            *target = next.get_real_position();
+
+            checkpoints.clear();
+            checkpoints.push_back(next);
+        }
+
+        profile.end_speed = 0.0;
+
+        active.coast = false;
+        active.outcome = MoveStatus::Cancelled;
+
         MazeRunnerResponse::Ack
     }
 
-    fn process_rotate(&mut self, direction: RotationDirection) -> MazeRunnerResponse {
-        self.runner.rotate(direction);
+    /// Drops the running move and everything queued, the runner stops where it is
+    fn cancel_moves(&mut self) {
+        let cancelled: Vec<u32> = self
+            .active_move
+            .take()
+            .map(|active| active.id)
+            .into_iter()
+            .chain(self.queued_moves.drain(..).map(|(id, _)| id))
+            .collect();
+
+        for id in cancelled {
+            self.record_move(id, MoveStatus::Cancelled);
+        }
 
-        let next_position = self.runner.get_real_position();
+        self.coasting = None;
 
-        {
-            let mut velocity = self.velocity.lock().unwrap();
+        let mut velocity = self.velocity.lock().unwrap();
 
-            velocity.translational = 0.0;
+        velocity.translational = 0.0;
+        velocity.rotational = 0.0;
+    }
 
-            velocity.rotational = match direction {
-                RotationDirection::Left => ROTATIONAL_VELOCITY,
-                RotationDirection::Right => -ROTATIONAL_VELOCITY,
+    /// Starts the next queued move and drives the running one a step further
+    fn process_moves(&mut self) -> Result<()> {
+        if self.active_move.is_none() {
+            let Some((id, command)) = self.queued_moves.pop_front() else {
+                return Ok(());
             };
+
+            if self.battery.lock().unwrap().is_cut_off() {
+                self.record_move(id, MoveStatus::Rejected);
+
+                return Ok(());
+            }
+
+            self.active_move = Some(match command {
+                MoveCommand::Straight {
+                    steps,
+                    diagonal,
+                    profile,
+                } => self.plan_straight(id, steps, diagonal, profile),
+                MoveCommand::Turn { kind, direction } => self.plan_turn(id, kind, direction),
+                MoveCommand::Rotate { direction, profile } => {
+                    self.plan_rotate(id, direction, profile)
+                }
+            });
         }
 
-        loop {
-            sleep(Duration::from_micros(100));
+        let Some(mut active) = self.active_move.take() else {
+            return Ok(());
+        };
 
-            let mut runner_position = self.runner_position.lock().unwrap();
+        let elapsed = active.last_update.elapsed();
 
-            if runner_position
-                .theta
-                .is_within(&next_position.theta, Angle::degrees(1.0))
-            {
-                runner_position.theta = next_position.theta;
+        if elapsed < Duration::from_micros(100) && !active.stages.is_empty() {
+            self.active_move = Some(active);
+
+            return Ok(());
+        }
+
+        active.last_update = Instant::now();
+
+        if self.drive_stage(&mut active, elapsed.as_secs_f64()) {
+            active.stages.pop_front();
+            active.stage_length = None;
+            active.stage_progress = 0.0;
+        }
+
+        if active.stages.is_empty() {
+            self.finish_move(active)
+        } else {
+            self.active_move = Some(active);
+
+            Ok(())
+        }
+    }
+
+    /// Moves forward by half cells, or wall midpoints on a diagonal, as one motion going as far
+    /// as the walls allow
+    fn plan_straight(
+        &mut self,
+        id: u32,
+        steps: usize,
+        diagonal: Option<bool>,
+        profile: MotionProfile,
+    ) -> ActiveMove<R, C> {
+        let mut active = ActiveMove::new(id);
+
+        let heading_matches = diagonal.map_or(true, |diagonal| {
+            diagonal == self.runner.get_orientation().is_diagonal()
+        });
+
+        if !heading_matches || !is_valid(&profile) {
+            active.outcome = MoveStatus::Rejected;
+
+            return active;
+        }
+
+        // A move queued while coasting carries on from where the previous move ended
+        self.coasting = None;
+
+        let mut runner = self.runner.clone();
+        let mut checkpoints = VecDeque::new();
+
+        for _ in 0..steps {
+            if runner.move_half(&self.maze.read()).is_err() {
+                active.outcome = MoveStatus::Crashed;
+
+                break;
+            }
+
+            checkpoints.push_back(runner.clone());
+        }
+
+        let Some(target) = checkpoints.back().map(|last| last.get_real_position()) else {
+            return active;
+        };
+
+        let profile = match active.outcome {
+            MoveStatus::Crashed => MotionProfile {
+                end_speed: 0.0,
+                ..profile
+            },
+            _ => profile,
+        };
+
+        active.coast = profile.end_speed > 0.0;
+        active.push(Stage::Straight {
+            target,
+            profile,
+            checkpoints,
+        });
+
+        active
+    }
+
+    fn plan_turn(&mut self, id: u32, kind: TurnKind, direction: TurnDirection) -> ActiveMove<R, C> {
+        let mut active = ActiveMove::new(id);
+
+        let from = self.get_resting_runner();
+
+        let (next, trajectory) = match from.plan_turn(kind, direction.into()) {
+            Ok(turn) => turn,
+            Err(e) => {
+                println!("{e}");
+
+                active.outcome = MoveStatus::Rejected;
+
+                return active;
+            }
+        };
+
+        self.plan_stop(&mut active);
+
+        if let Err(e) = trajectory.check_clearance(&self.maze.read()) {
+            println!("{e}");
+
+            active.outcome = MoveStatus::Crashed;
+
+            return active;
+        }
+
+        // Segments run one after another without stopping in between
+        for (segment, target) in trajectory.get_steps() {
+            active.push(match segment {
+                Segment::Straight(_) => Stage::Straight {
+                    target,
+                    profile: MotionProfile {
+                        end_speed: TRANSLATIONAL_VELOCITY,
+                        ..TRANSLATIONAL_PROFILE
+                    },
+                    checkpoints: VecDeque::new(),
+                },
+                Segment::Arc(radius, angle) => Stage::Arc {
+                    target,
+                    rotational: TRANSLATIONAL_VELOCITY / radius * angle.signum(),
+                },
+                Segment::Spin(angle) => Stage::Rotate {
+                    target,
+                    sign: angle.signum(),
+                    profile: ROTATIONAL_PROFILE,
+                },
+            });
+        }
+
+        active.end_state = Some(next);
+
+        active
+    }
+
+    fn plan_rotate(
+        &mut self,
+        id: u32,
+        direction: RotationDirection,
+        profile: MotionProfile,
+    ) -> ActiveMove<R, C> {
+        let mut active = ActiveMove::new(id);
+
+        let mut next = self.get_resting_runner();
+
+        if next.is_at_edge() || !is_valid(&profile) {
+            active.outcome = MoveStatus::Rejected;
+
+            return active;
+        }
+
+        self.plan_stop(&mut active);
+
+        next.rotate(direction);
+
+        let sign = match direction {
+            RotationDirection::Left => 1.0,
+            RotationDirection::Right => -1.0,
+        };
+
+        active.push(Stage::Rotate {
+            target: next.get_real_position(),
+            sign,
+            profile,
+        });
+
+        active.end_state = Some(next);
+
+        active
+    }
+
+    /// Sets the velocity for the current stage, true once the runner has arrived at its end
+    fn drive_stage(&mut self, active: &mut ActiveMove<R, C>, elapsed: f64) -> bool {
+        let position = self.runner_position.lock().unwrap().clone();
+
+        let Some(stage) = active.stages.front_mut() else {
+            return true;
+        };
+
+        match stage {
+            Stage::Straight {
+                target,
+                profile,
+                checkpoints,
+            } => {
+                let heading = target.theta;
+
+                let ahead = |point: &Position<R>| {
+                    (point.x - position.x) * heading.cos() + (point.y - position.y) * heading.sin()
+                };
+
+                // The discrete state follows the runner as it passes every half cell
+                while checkpoints
+                    .front()
+                    .is_some_and(|next| ahead(&next.get_real_position()) < 2.0)
+                {
+                    if let Some(next) = checkpoints.pop_front() {
+                        self.set_runner(next);
+                    }
+                }
+
+                let remaining = ahead(target);
+                let length = *active.stage_length.get_or_insert(remaining.max(1.0));
+
+                active.stage_progress = (1.0 - remaining / length).clamp(0.0, 1.0);
+
+                if remaining < 2.0 {
+                    let mut runner_position = self.runner_position.lock().unwrap();
+
+                    runner_position.x = target.x;
+                    runner_position.y = target.y;
+
+                    self.velocity.lock().unwrap().translational = profile.end_speed;
+
+                    return true;
+                }
 
                 let mut velocity = self.velocity.lock().unwrap();
 
+                let braking_speed =
+                    (profile.end_speed.powi(2) + 2.0 * profile.acceleration * remaining).sqrt();
+
                 velocity.rotational = 0.0;
+                velocity.translational = (velocity.translational.max(0.0)
+                    + profile.acceleration * elapsed)
+                    .min(profile.max_speed)
+                    .min(braking_speed);
 
-                break;
+                false
+            }
+            Stage::Arc { target, rotational } => {
+                let remaining = (target.theta - position.theta).abs().as_radians();
+                let length = *active.stage_length.get_or_insert(remaining.max(1e-3));
+
+                active.stage_progress = (1.0 - remaining / length).clamp(0.0, 1.0);
+
+                if position.theta.is_within(&target.theta, Angle::degrees(1.0)) {
+                    *self.runner_position.lock().unwrap() = target.clone();
+
+                    return true;
+                }
+
+                let mut velocity = self.velocity.lock().unwrap();
+
+                velocity.translational = TRANSLATIONAL_VELOCITY;
+                velocity.rotational = *rotational;
+
+                false
+            }
+            Stage::Rotate {
+                target,
+                sign,
+                profile,
+            } => {
+                let remaining = (target.theta - position.theta).abs().as_radians();
+                let length = *active.stage_length.get_or_insert(remaining.max(1e-3));
+
+                active.stage_progress = (1.0 - remaining / length).clamp(0.0, 1.0);
+
+                if remaining < Angle::degrees(1.0).as_radians() {
+                    self.runner_position.lock().unwrap().theta = target.theta;
+                    self.velocity.lock().unwrap().rotational = 0.0;
+
+                    return true;
+                }
+
+                let mut velocity = self.velocity.lock().unwrap();
+
+                let speed = (velocity.rotational.abs() + profile.acceleration * elapsed)
+                    .min(profile.max_speed)
+                    .min((2.0 * profile.acceleration * remaining).sqrt());
+
+                velocity.translational = 0.0;
+                velocity.rotational = *sign * speed;
+
+                false
             }
         }
+    }
 
-        MazeRunnerResponse::Ack
+    fn finish_move(&mut self, active: ActiveMove<R, C>) -> Result<()> {
+        if let Some(next) = active.end_state {
+            self.set_runner(next);
+        }
+
+        match active.outcome {
+            MoveStatus::Crashed => {
+                self.stop_runner();
+                self.process_crash()?;
+            }
+            MoveStatus::Done if active.coast => self.start_coasting(),
+            // Nothing was started, a coasting runner keeps going
+            MoveStatus::Rejected => {}
+            _ => self.stop_runner(),
+        }
+
+        self.record_move(active.id, active.outcome);
+
+        Ok(())
+    }
+
+    /// Takes on a new discrete state, keeping the run log up to date with the cells passed
+    fn set_runner(&mut self, next: MazerRunner<R, C>) {
+        let previous_cell = self.runner.get_cell();
+
+        self.runner = next;
+
+        if self.runner.get_cell() != previous_cell {
+            self.run_log.lock().unwrap().runner_moved(
+                &self.maze.read(),
+                previous_cell,
+                self.runner.get_cell(),
+            );
+        }
+    }
+
+    /// Where the runner comes to rest, the half cell it is coasting to if it is still moving
+    fn get_resting_runner(&self) -> MazerRunner<R, C> {
+        self.coasting.clone().unwrap_or_else(|| self.runner.clone())
+    }
+
+    /// Brakes a coasting runner to the half cell ahead as the first stage, so turns and
+    /// rotations start from where the runner is rather than where the last move ended
+    fn plan_stop(&mut self, active: &mut ActiveMove<R, C>) {
+        let Some(next) = self.coasting.take() else {
+            self.stop_runner();
+
+            return;
+        };
+
+        let speed = self.velocity.lock().unwrap().translational;
+
+        active.push(Stage::Straight {
+            target: next.get_real_position(),
+            profile: MotionProfile {
+                max_speed: speed.max(TRANSLATIONAL_VELOCITY),
+                end_speed: 0.0,
+                ..TRANSLATIONAL_PROFILE
+            },
+            checkpoints: VecDeque::from([next]),
+        });
+    }
+
+    /// Keeps the runner going towards the next half cell, unless a wall is in the way
+    fn start_coasting(&mut self) {
+        let mut next = self.runner.clone();
+
+        if next.move_half(&self.maze.read()).is_ok() {
+            self.coasting = Some(next);
+        } else {
+            self.stop_runner();
+        }
+    }
+
+    fn process_coasting(&mut self) {
+        let Some(next_position) = self.coasting.as_ref().map(|next| next.get_real_position())
+        else {
+            return;
+        };
+
+        {
+            let mut runner_position = self.runner_position.lock().unwrap();
+
+            if (runner_position.x - next_position.x).abs() >= 2.0
+                || (runner_position.y - next_position.y).abs() >= 2.0
+            {
+                return;
+            }
+
+            runner_position.x = next_position.x;
+            runner_position.y = next_position.y;
+
+            self.velocity.lock().unwrap().translational = 0.0;
+        }
+
+        if let Some(next) = self.coasting.take() {
+            self.set_runner(next);
+        }
+    }
+
+    /// Blocks until all queued moves are done and a continuous move has come to a stop
+    pub fn settle(&mut self) -> Result<()> {
+        while self.active_move.is_some() || !self.queued_moves.is_empty() || self.coasting.is_some()
+        {
+            sleep(Duration::from_micros(100));
+
+            self.process_moves()?;
+            self.process_coasting();
+            self.process_battery();
+        }
+
+        Ok(())
+    }
+
+    /// Drains the battery with the present motor load, the runner stops below the cutoff
+    fn process_battery(&mut self) {
+        let velocity = self.velocity.lock().unwrap().clone();
+
+        let (cut_off, voltage) = {
+            let mut battery = self.battery.lock().unwrap();
+
+            let cut_off = battery.update(velocity.translational, velocity.rotational);
+
+            (cut_off, battery.get_voltage())
+        };
+
+        if cut_off {
+            println!("Battery at {voltage:.2} V is below the cutoff, motors switched off");
+
+            self.cancel_moves();
+        }
+    }
+
+    /// Stops any motion and puts the runner back where its discrete state says it is
+    fn stop_runner(&mut self) {
+        self.coasting = None;
+
+        {
+            let mut velocity = self.velocity.lock().unwrap();
+
+            velocity.translational = 0.0;
+            velocity.rotational = 0.0;
+        }
+
+        *self.runner_position.lock().unwrap() = self.runner.get_real_position();
     }
 
     fn process_buttons(&self) -> MazeRunnerResponse {
@@ -236,6 +1136,52 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
         MazeRunnerResponse::Buttons(response)
     }
 
+    fn process_set_led(&mut self, index: u8, color: LedColor) -> MazeRunnerResponse {
+        match self.outputs.lock().unwrap().set_led(index as usize, color) {
+            Ok(()) => MazeRunnerResponse::Ack,
+            Err(e) => {
+                println!("{e}");
+
+                MazeRunnerResponse::Error
+            }
+        }
+    }
+
+    fn process_beep(&mut self, frequency: u16, duration: u16) -> MazeRunnerResponse {
+        self.outputs
+            .lock()
+            .unwrap()
+            .beep(frequency, Duration::from_millis(duration as u64));
+
+        MazeRunnerResponse::Ack
+    }
+
+    fn process_set_display_line(
+        &mut self,
+        line: u8,
+        text: [u8; DISPLAY_COLUMNS],
+    ) -> MazeRunnerResponse {
+        match self
+            .outputs
+            .lock()
+            .unwrap()
+            .set_display_line(line as usize, text)
+        {
+            Ok(()) => MazeRunnerResponse::Ack,
+            Err(e) => {
+                println!("{e}");
+
+                MazeRunnerResponse::Error
+            }
+        }
+    }
+
+    fn process_clear_display(&mut self) -> MazeRunnerResponse {
+        self.outputs.lock().unwrap().clear_display();
+
+        MazeRunnerResponse::Ack
+    }
+
     fn process_clear_cell(&mut self, x: usize, y: usize) -> MazeRunnerResponse {
         let cell = match Cell::new(x, y) {
             Ok(cell) => cell,
@@ -306,7 +1252,13 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
         })
     }
 
-    fn process_set_velocity(&self, translational: f64, rotational: f64) -> MazeRunnerResponse {
+    fn process_set_velocity(&mut self, translational: f64, rotational: f64) -> MazeRunnerResponse {
+        self.cancel_moves();
+
+        if self.battery.lock().unwrap().is_cut_off() {
+            return MazeRunnerResponse::Error;
+        }
+
         let mut velocity = self.velocity.lock().unwrap();
 
         velocity.translational = translational;
@@ -314,4 +1266,335 @@ impl<const R: usize, const C: usize> SimEnvironment<R, C> {
 
         MazeRunnerResponse::Ack
     }
+
+    fn process_battery_readout(&self) -> MazeRunnerResponse {
+        let battery = self.battery.lock().unwrap();
+
+        MazeRunnerResponse::Battery(BatteryReadout {
+            voltage: battery.get_voltage(),
+            current: battery.get_current(),
+            cut_off: battery.is_cut_off(),
+        })
+    }
+
+    fn process_goal_cell(&self, index: usize) -> MazeRunnerResponse {
+        let cell = self
+            .maze
+            .read()
+            .get_goal_cells()
+            .get(index)
+            .map(|cell| (cell.x, cell.y));
+
+        MazeRunnerResponse::GoalCell(cell)
+    }
+}
+
+fn straight(steps: usize, diagonal: Option<bool>, profile: MotionProfile) -> MoveCommand {
+    MoveCommand::Straight {
+        steps,
+        diagonal,
+        profile,
+    }
+}
+
+fn is_valid(profile: &MotionProfile) -> bool {
+    profile.max_speed > 0.0
+        && profile.acceleration > 0.0
+        && (0.0..=profile.max_speed).contains(&profile.end_speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc,
+        },
+        thread,
+    };
+
+    use super::*;
+    use crate::{runner::MazeOrientation, velocity::VelocityEnvironment};
+
+    const FAST: MotionProfile = MotionProfile {
+        max_speed: 2000.0,
+        acceleration: 20000.0,
+        end_speed: 0.0,
+    };
+
+    /// Runs the request with the velocity integrated alongside, like the simulator does
+    fn request(
+        environment: &mut SimEnvironment<4, 4>,
+        request: MazeRunnerRequest,
+    ) -> MazeRunnerResponse {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let velocity = VelocityEnvironment::new(
+            environment.get_runner_position_handle(),
+            environment.get_velocity_handle(),
+        );
+
+        let handle = {
+            let stop = stop.clone();
+
+            thread::spawn(move || velocity.process(stop))
+        };
+
+        let response = environment.process_request(request).unwrap();
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap().unwrap();
+
+        response
+    }
+
+    /// Open 4x4 maze with outer walls, the runner starts at (0, 0) facing north
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for i in 0..4 {
+            maze.update_cell_state(Cell::new(i, 0).unwrap(), CellState::SouthWall, true);
+            maze.update_cell_state(Cell::new(i, 3).unwrap(), CellState::NorthWall, true);
+            maze.update_cell_state(Cell::new(0, i).unwrap(), CellState::WestWall, true);
+            maze.update_cell_state(Cell::new(3, i).unwrap(), CellState::EastWall, true);
+        }
+
+        maze.set_start_cell(Cell::new(0, 0).unwrap());
+        maze.set_goal_cell(Cell::new(3, 3).unwrap());
+
+        maze
+    }
+
+    fn environment() -> SimEnvironment<4, 4> {
+        let mut environment = SimEnvironment::new(maze()).unwrap();
+
+        request(&mut environment, MazeRunnerRequest::Initialize);
+
+        environment
+    }
+
+    fn continuous(half_cells: u8) -> MazeRunnerRequest {
+        MazeRunnerRequest::MoveProfiled {
+            steps: half_cells,
+            profile: MotionProfile {
+                end_speed: 200.0,
+                ..FAST
+            },
+        }
+    }
+
+    fn rotate_right() -> MazeRunnerRequest {
+        MazeRunnerRequest::Rotate90Profiled {
+            direction: TurnDirection::Right,
+            profile: MotionProfile {
+                max_speed: 20.0,
+                acceleration: 200.0,
+                end_speed: 0.0,
+            },
+        }
+    }
+
+    fn get_y(environment: &SimEnvironment<4, 4>) -> f64 {
+        environment.get_runner_position_handle().lock().unwrap().y
+    }
+
+    #[test]
+    fn rotation_after_a_continuous_move_stops_ahead() {
+        let mut environment = environment();
+
+        request(&mut environment, continuous(1));
+
+        let y = get_y(&environment);
+
+        assert!(matches!(
+            request(&mut environment, rotate_right()),
+            MazeRunnerResponse::Ack
+        ));
+
+        // Braked to the center of the cell it was coasting into rather than back at the wall
+        assert!(get_y(&environment) > y);
+        assert_eq!(get_y(&environment), 270.0);
+        assert_eq!(
+            environment.get_runner().get_cell(),
+            Cell::new(0, 1).unwrap()
+        );
+        assert_eq!(
+            environment.get_runner().get_orientation(),
+            MazeOrientation::East
+        );
+    }
+
+    #[test]
+    fn rejected_rotation_keeps_the_runner_coasting() {
+        let mut environment = environment();
+
+        request(&mut environment, continuous(2));
+
+        let y = get_y(&environment);
+
+        // Coasting onto the wall midpoint, where it can't rotate in place
+        assert!(matches!(
+            request(&mut environment, rotate_right()),
+            MazeRunnerResponse::Error
+        ));
+
+        assert!(get_y(&environment) >= y);
+        assert!(environment.coasting.is_some());
+    }
+
+    #[test]
+    fn failing_request_and_reconnect_keep_the_environment_running() {
+        let environment = SimEnvironment::new(maze()).unwrap();
+        let shared_maze = environment.get_maze_handle();
+
+        let (request_tx, request_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::channel();
+
+        thread::spawn(move || environment.process(request_rx, response_tx));
+
+        let request = |request| {
+            request_tx.send(SimEvent::Request(request)).unwrap();
+
+            response_rx
+                .recv()
+                .expect("Environment should still be running")
+        };
+
+        let set_start_walls = |value| {
+            shared_maze.update(|maze| {
+                let start = Cell::new(0, 0).unwrap();
+
+                maze.update_cell_state(start, CellState::NorthWall, value);
+                maze.update_cell_state(start, CellState::EastWall, value);
+            })
+        };
+
+        // With the start cell walled in the runner can't be put back on it
+        set_start_walls(true);
+
+        assert!(matches!(
+            request(MazeRunnerRequest::Initialize),
+            MazeRunnerResponse::Error
+        ));
+
+        request_tx.send(SimEvent::ClientDisconnected).unwrap();
+
+        set_start_walls(false);
+
+        assert!(matches!(
+            request(MazeRunnerRequest::Initialize),
+            MazeRunnerResponse::Ack
+        ));
+        assert!(matches!(
+            request(MazeRunnerRequest::GetWallFront),
+            MazeRunnerResponse::WallDetected(false)
+        ));
+    }
+
+    #[test]
+    fn led_index_beyond_the_count_is_rejected() {
+        let mut environment = environment();
+
+        environment.set_led_count(2);
+
+        let set_led = |index| MazeRunnerRequest::SetLed {
+            index,
+            color: LedColor::ON,
+        };
+
+        assert!(matches!(
+            environment.process_request(set_led(1)).unwrap(),
+            MazeRunnerResponse::Ack
+        ));
+
+        for index in [2, u8::MAX] {
+            assert!(matches!(
+                environment.process_request(set_led(index)).unwrap(),
+                MazeRunnerResponse::Error
+            ));
+        }
+
+        assert_eq!(environment.outputs.lock().unwrap().get_leds().len(), 2);
+    }
+
+    /// Battery whose cutoff is above its full voltage, the first update switches the motors off
+    fn cut_off_battery() -> BatteryModel {
+        BatteryModel {
+            cutoff_voltage: 9.0,
+            ..BatteryModel::default()
+        }
+    }
+
+    fn move_status(environment: &mut SimEnvironment<4, 4>, id: u32) -> MoveStatus {
+        match environment
+            .process_request(MazeRunnerRequest::GetMoveStatus { id })
+            .unwrap()
+        {
+            MazeRunnerResponse::MoveStatus(status) => status,
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn battery_cutoff_cancels_queued_moves() {
+        let mut environment = environment();
+
+        environment.set_battery_model(cut_off_battery());
+
+        let ids: Vec<u32> = (0..2)
+            .map(|_| {
+                match environment
+                    .process_request(MazeRunnerRequest::StartMove {
+                        steps: 2,
+                        profile: FAST,
+                    })
+                    .unwrap()
+                {
+                    MazeRunnerResponse::MoveStarted(id) => id,
+                    response => panic!("Unexpected response {:?}", response),
+                }
+            })
+            .collect();
+
+        for id in &ids {
+            assert_eq!(move_status(&mut environment, *id), MoveStatus::Queued);
+        }
+
+        environment.process_battery();
+
+        for id in &ids {
+            assert_eq!(move_status(&mut environment, *id), MoveStatus::Cancelled);
+        }
+
+        assert!(environment.queued_moves.is_empty());
+        assert!(environment.active_move.is_none());
+    }
+
+    #[test]
+    fn set_velocity_is_rejected_after_cutoff() {
+        let mut environment = environment();
+
+        let set_velocity = || MazeRunnerRequest::SetVelocity {
+            translational: 200.0,
+            rotational: 0.0,
+        };
+
+        assert!(matches!(
+            environment.process_request(set_velocity()).unwrap(),
+            MazeRunnerResponse::Ack
+        ));
+
+        environment.set_battery_model(cut_off_battery());
+        environment.process_battery();
+
+        assert!(matches!(
+            environment.process_request(set_velocity()).unwrap(),
+            MazeRunnerResponse::Error
+        ));
+
+        let velocity = environment.get_velocity_handle().lock().unwrap().clone();
+
+        assert_eq!(velocity.translational, 0.0);
+        assert_eq!(velocity.rotational, 0.0);
+    }
 }
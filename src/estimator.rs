@@ -0,0 +1,542 @@
+use std::{
+    collections::BinaryHeap,
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2},
+    fmt,
+    time::Duration,
+};
+
+use crate::{
+    maze::{Cell, CellState, Maze},
+    position::Millimeters,
+    runner::MazeOrientation,
+    search::State,
+    simulator::CELL_SIZE_MM,
+    solver::trapezoid_time,
+};
+
+const HALF_CELL_MM: Millimeters = CELL_SIZE_MM as f64 / 2.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct FastRunProfile {
+    /// Top speed on orthogonal straights [mm/s]
+    pub max_speed: f64,
+    /// Top speed on diagonal straights [mm/s]
+    pub max_diagonal_speed: f64,
+    /// Acceleration and deceleration on straights [mm/s^2]
+    pub acceleration: f64,
+    /// Centripetal acceleration limiting the speed through corners [mm/s^2]
+    pub lateral_acceleration: f64,
+    /// Radius of the arc joining orthogonal and diagonal straights
+    pub radius_45: Millimeters,
+    /// Radius of the arc joining two diagonal straights
+    pub radius_90: Millimeters,
+}
+
+impl Default for FastRunProfile {
+    fn default() -> Self {
+        Self {
+            max_speed: 3000.0,
+            max_diagonal_speed: 2000.0,
+            acceleration: 8000.0,
+            lateral_acceleration: 6000.0,
+            radius_45: 90.0,
+            radius_90: 64.0,
+        }
+    }
+}
+
+impl FastRunProfile {
+    fn corner(&self, angle: f64) -> Corner {
+        let radius = if angle > FRAC_PI_4 {
+            self.radius_90
+        } else {
+            self.radius_45
+        };
+
+        Corner {
+            tangent: radius * (angle / 2.0).tan(),
+            arc: radius * angle,
+            speed: (self.lateral_acceleration * radius)
+                .sqrt()
+                .min(self.max_diagonal_speed),
+        }
+    }
+
+    fn straight_speed(&self, heading: usize) -> f64 {
+        if heading % 2 == 0 {
+            self.max_speed
+        } else {
+            self.max_diagonal_speed
+        }
+    }
+}
+
+/// Arc replacing the sharp corner between two straights
+#[derive(Copy, Clone)]
+struct Corner {
+    tangent: Millimeters,
+    arc: Millimeters,
+    speed: f64,
+}
+
+impl Corner {
+    fn none(speed: f64) -> Self {
+        Self {
+            tangent: 0.0,
+            arc: 0.0,
+            speed,
+        }
+    }
+
+    fn half_arc_time(&self) -> f64 {
+        if self.arc > 0.0 {
+            self.arc / 2.0 / self.speed
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Move {
+    /// Straight along the maze axes, in cells
+    Straight(f64),
+    /// Straight across cells, in half diagonals
+    Diagonal(usize),
+    /// Turn in degrees, positive to the right
+    Turn(i32),
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Move::Straight(cells) => write!(f, "F{}", cells),
+            Move::Diagonal(steps) => write!(f, "D{}", steps),
+            Move::Turn(angle) if *angle > 0 => write!(f, "R{}", angle),
+            Move::Turn(angle) => write!(f, "L{}", -angle),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RunEstimate {
+    pub moves: Vec<Move>,
+    pub time: Duration,
+}
+
+impl RunEstimate {
+    pub fn get_diagonal_count(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|step| matches!(step, Move::Diagonal(_)))
+            .count()
+    }
+
+    pub fn get_turn_count(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|step| matches!(step, Move::Turn(_)))
+            .count()
+    }
+}
+
+type Point = (i32, i32);
+
+/// Fast run estimator searching paths through the midpoints of the open walls
+///
+/// Points are on a half cell grid, cell centers have odd and posts even coordinates. A path is
+/// a polyline of straights joined by 45 degree corners between orthogonal and diagonal
+/// straights and by 90 degree corners between diagonal straights. Corners are rounded off
+/// with the radii of the profile and taken at the speed the lateral acceleration allows, so
+/// straights between them have to be long enough to brake or speed up from one to the next.
+pub struct RunEstimator<'a, const R: usize, const C: usize> {
+    maze: &'a Maze<R, C>,
+    profile: FastRunProfile,
+}
+
+impl<'a, const R: usize, const C: usize> RunEstimator<'a, R, C> {
+    pub fn new(maze: &'a Maze<R, C>) -> Self {
+        Self {
+            maze,
+            profile: FastRunProfile::default(),
+        }
+    }
+
+    pub fn profile(mut self, profile: FastRunProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// States are corners, a point with the incoming and outgoing heading
+    fn index(point: Point, heading_in: usize, heading_out: usize) -> usize {
+        let point = point.0 as usize * (2 * R + 1) + point.1 as usize;
+
+        (point * 8 + heading_in) * 8 + heading_out
+    }
+
+    fn state(index: usize) -> (Point, usize, usize) {
+        let heading_out = index % 8;
+        let heading_in = index / 8 % 8;
+        let point = index / 64;
+
+        (
+            ((point / (2 * R + 1)) as i32, (point % (2 * R + 1)) as i32),
+            heading_in,
+            heading_out,
+        )
+    }
+
+    fn has_wall(&self, x: i32, y: i32, wall: CellState) -> bool {
+        self.maze
+            .get_cell_state(Cell {
+                x: x as usize,
+                y: y as usize,
+            })
+            .contains(wall)
+    }
+
+    /// Whether the wall at the midpoint between two posts is missing
+    fn is_open(&self, (x, y): Point) -> bool {
+        let (width, height) = (2 * C as i32, 2 * R as i32);
+
+        if x % 2 != 0 && y % 2 == 0 {
+            y > 0 && y < height && !self.has_wall((x - 1) / 2, y / 2, CellState::SouthWall)
+        } else if x % 2 == 0 && y % 2 != 0 {
+            x > 0 && x < width && !self.has_wall(x / 2, (y - 1) / 2, CellState::WestWall)
+        } else {
+            false
+        }
+    }
+
+    fn borders_goal(&self, (x, y): Point) -> bool {
+        let cells = if y % 2 == 0 {
+            [((x - 1) / 2, y / 2 - 1), ((x - 1) / 2, y / 2)]
+        } else {
+            [(x / 2 - 1, (y - 1) / 2), (x / 2, (y - 1) / 2)]
+        };
+
+        cells.into_iter().any(|(x, y)| {
+            x >= 0
+                && y >= 0
+                && Cell::<R, C>::new(x as usize, y as usize)
+                    .is_ok_and(|cell| self.maze.is_goal_cell(cell))
+        })
+    }
+
+    /// Next open point along the heading and the distance to it
+    fn step(&self, (x, y): Point, heading: usize) -> Option<(Point, Millimeters)> {
+        // Headings are eighths clockwise from north, even ones are orthogonal
+        let (dx, dy) = MazeOrientation::from_eighths(heading).get_step();
+        let (dx, dy) = (dx as i32, dy as i32);
+
+        let (next, length) = if heading % 2 != 0 {
+            // Diagonals run between the midpoints of two walls of the same cell
+            if x % 2 == y % 2 {
+                return None;
+            }
+
+            ((x + dx, y + dy), HALF_CELL_MM * SQRT_2)
+        } else if x % 2 != 0 && y % 2 != 0 {
+            ((x + dx, y + dy), HALF_CELL_MM)
+        } else if (dx != 0 && x % 2 == 0) || (dy != 0 && y % 2 == 0) {
+            ((x + 2 * dx, y + 2 * dy), 2.0 * HALF_CELL_MM)
+        } else {
+            return None;
+        };
+
+        self.is_open(next).then_some((next, length))
+    }
+
+    fn turn_angle(heading_in: usize, heading_out: usize) -> Option<f64> {
+        let diagonal_in = heading_in % 2 != 0;
+        let diagonal_out = heading_out % 2 != 0;
+
+        match (heading_out + 8 - heading_in) % 8 {
+            1 | 7 if diagonal_in != diagonal_out => Some(FRAC_PI_4),
+            2 | 6 if diagonal_in && diagonal_out => Some(FRAC_PI_2),
+            _ => None,
+        }
+    }
+
+    /// Time from the middle of one corner to the middle of the next, `None` when they overlap
+    fn straight_time(
+        &self,
+        heading: usize,
+        distance: Millimeters,
+        from: Corner,
+        to: Corner,
+    ) -> Option<f64> {
+        let straight = distance - from.tangent - to.tangent;
+
+        if straight < 0.0 {
+            return None;
+        }
+
+        // The straight has to be long enough to brake for the next corner
+        let braking = (from.speed.powi(2) - to.speed.powi(2)) / (2.0 * self.profile.acceleration);
+
+        if braking > straight {
+            return None;
+        }
+
+        // and to speed up to it, only the goal is crossed at whatever speed is reached
+        let accelerating =
+            (to.speed.powi(2) - from.speed.powi(2)) / (2.0 * self.profile.acceleration);
+
+        if to.arc > 0.0 && accelerating > straight {
+            return None;
+        }
+
+        let time = trapezoid_time(
+            straight,
+            from.speed,
+            to.speed,
+            self.profile.straight_speed(heading),
+            self.profile.acceleration,
+        );
+
+        Some(from.half_arc_time() + time + to.half_arc_time())
+    }
+
+    pub fn estimate(&self) -> Option<RunEstimate> {
+        let start = self.maze.get_start_cell();
+        let start = (2 * start.x as i32 + 1, 2 * start.y as i32 + 1);
+
+        let terminal = Self::index((2 * C as i32, 2 * R as i32), 7, 7) + 1;
+
+        let mut costs = vec![f64::INFINITY; terminal + 1];
+        let mut previous = vec![None; terminal + 1];
+        let mut heap = BinaryHeap::new();
+
+        for heading in [0, 2, 4, 6] {
+            let index = Self::index(start, heading, heading);
+
+            costs[index] = 0.0;
+            heap.push(State { cost: 0.0, index });
+        }
+
+        while let Some(State { cost, index }) = heap.pop() {
+            if index == terminal {
+                break;
+            }
+
+            if cost > costs[index] {
+                continue;
+            }
+
+            let (point, heading_in, heading) = Self::state(index);
+
+            let from = match Self::turn_angle(heading_in, heading) {
+                Some(angle) => self.profile.corner(angle),
+                None => Corner::none(0.0),
+            };
+
+            let mut next = point;
+            let mut distance = 0.0;
+
+            while let Some((following, length)) = self.step(next, heading) {
+                next = following;
+                distance += length;
+
+                let mut push = |next_index: usize, time: Option<f64>| {
+                    let Some(time) = time else {
+                        return;
+                    };
+
+                    if cost + time < costs[next_index] {
+                        costs[next_index] = cost + time;
+                        previous[next_index] = Some(index);
+
+                        heap.push(State {
+                            cost: cost + time,
+                            index: next_index,
+                        });
+                    }
+                };
+
+                // The run ends crossing into the goal, at whatever speed the straight allows
+                if self.borders_goal(next) {
+                    let to = Corner::none(self.profile.straight_speed(heading));
+
+                    push(terminal, self.straight_time(heading, distance, from, to));
+
+                    break;
+                }
+
+                for heading_out in 0..8 {
+                    let Some(angle) = Self::turn_angle(heading, heading_out) else {
+                        continue;
+                    };
+
+                    if self.step(next, heading_out).is_none() {
+                        continue;
+                    }
+
+                    let to = self.profile.corner(angle);
+
+                    push(
+                        Self::index(next, heading, heading_out),
+                        self.straight_time(heading, distance, from, to),
+                    );
+                }
+            }
+        }
+
+        let mut corners = vec![previous[terminal]?];
+
+        while let Some(index) = previous[*corners.last().unwrap()] {
+            corners.push(index);
+        }
+
+        corners.reverse();
+
+        Some(RunEstimate {
+            moves: self.moves(&corners),
+            time: Duration::from_secs_f64(costs[terminal]),
+        })
+    }
+
+    fn moves(&self, corners: &[usize]) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        for (i, index) in corners.iter().enumerate() {
+            let (point, heading_in, heading) = Self::state(*index);
+
+            if i > 0 {
+                let angle = match (heading + 8 - heading_in) % 8 {
+                    1 => 45,
+                    2 => 90,
+                    6 => -90,
+                    _ => -45,
+                };
+
+                moves.push(Move::Turn(angle));
+            }
+
+            // Straights run up to the next corner, the last one up to the goal
+            let end = corners.get(i + 1).map(|index| Self::state(*index).0);
+
+            let mut next = point;
+            let mut steps = 0;
+            let mut distance = 0.0;
+
+            while let Some((following, length)) = self.step(next, heading) {
+                next = following;
+                steps += 1;
+                distance += length;
+
+                if Some(next) == end || (end.is_none() && self.borders_goal(next)) {
+                    break;
+                }
+            }
+
+            if heading % 2 == 0 {
+                moves.push(Move::Straight(distance / CELL_SIZE_MM as f64));
+            } else {
+                moves.push(Move::Diagonal(steps));
+            }
+        }
+
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open 4x4 maze with outer walls, the start at (0, 0) only open to the north
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for i in 0..4 {
+            maze.update_cell_state(Cell { x: i, y: 0 }, CellState::SouthWall, true);
+            maze.update_cell_state(Cell { x: i, y: 3 }, CellState::NorthWall, true);
+            maze.update_cell_state(Cell { x: 0, y: i }, CellState::WestWall, true);
+            maze.update_cell_state(Cell { x: 3, y: i }, CellState::EastWall, true);
+        }
+
+        maze.update_cell_state(Cell { x: 0, y: 0 }, CellState::EastWall, true);
+
+        maze.set_start_cell(Cell { x: 0, y: 0 });
+
+        maze
+    }
+
+    #[test]
+    fn straight_run_takes_the_trapezoid_time() {
+        let mut maze = maze();
+
+        for y in 1..4 {
+            maze.update_cell_state(Cell { x: 0, y }, CellState::EastWall, true);
+        }
+
+        maze.set_goal_cell(Cell { x: 0, y: 3 });
+
+        let profile = FastRunProfile::default();
+
+        let estimate = RunEstimator::new(&maze)
+            .profile(profile)
+            .estimate()
+            .unwrap();
+
+        assert_eq!(estimate.moves, vec![Move::Straight(2.5)]);
+
+        // From the start cell center up to the goal, too short to reach the top speed
+        let distance = 2.5 * CELL_SIZE_MM as f64;
+
+        assert!(profile.max_speed.powi(2) / (2.0 * profile.acceleration) > distance);
+
+        let expected = (2.0 * distance / profile.acceleration).sqrt();
+
+        assert!((estimate.time.as_secs_f64() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn run_with_corners_is_never_faster_than_a_straight_line() {
+        let mut maze = maze();
+
+        maze.set_goal_cell(Cell { x: 3, y: 3 });
+
+        let profile = FastRunProfile::default();
+
+        let estimate = RunEstimator::new(&maze)
+            .profile(profile)
+            .estimate()
+            .unwrap();
+
+        assert!(estimate.get_turn_count() > 0);
+
+        // Straight line from the start cell center to the nearest side of the goal cell
+        let distance = HALF_CELL_MM * ((6.0f64 - 1.0).powi(2) + (7.0f64 - 1.0).powi(2)).sqrt();
+
+        let bound = trapezoid_time(
+            distance,
+            0.0,
+            profile.max_speed,
+            profile.max_speed,
+            profile.acceleration,
+        );
+
+        assert!(estimate.time.as_secs_f64() >= bound);
+    }
+
+    #[test]
+    fn corner_out_of_reach_of_a_short_straight_is_skipped() {
+        let maze = maze();
+        let estimator = RunEstimator::new(&maze);
+
+        let corner = estimator.profile.corner(FRAC_PI_4);
+        let start = Corner::none(0.0);
+
+        // Just long enough to speed up from rest to the corner speed
+        let reach = corner.speed.powi(2) / (2.0 * estimator.profile.acceleration);
+
+        assert!(estimator
+            .straight_time(0, reach + corner.tangent + 1.0, start, corner)
+            .is_some());
+        assert!(estimator
+            .straight_time(0, reach + corner.tangent - 1.0, start, corner)
+            .is_none());
+    }
+}
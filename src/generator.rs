@@ -0,0 +1,345 @@
+use anyhow::{bail, Result};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    maze::{Cell, CellState, GoalArea, Maze},
+    runner::{MazeOrientation, RotationDirection},
+    validation::{MazeValidator, Severity},
+};
+
+#[derive(Copy, Clone, PartialEq)]
+enum Turn {
+    None,
+    Left,
+    Right,
+}
+
+struct Step<const R: usize, const C: usize> {
+    cell: Cell<R, C>,
+    direction: Option<MazeOrientation>,
+    turn: Turn,
+}
+
+pub struct MazeGenerator<const R: usize, const C: usize> {
+    rng: StdRng,
+    loops: f64,
+    straights: f64,
+    diagonals: f64,
+}
+
+impl<const R: usize, const C: usize> MazeGenerator<R, C> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            loops: 0.05,
+            straights: 0.5,
+            diagonals: 0.3,
+        }
+    }
+
+    /// Fraction of the remaining inner walls removed after carving to create loops
+    pub fn loops(mut self, loops: f64) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    /// Probability of carving straight ahead when possible
+    pub fn straights(mut self, straights: f64) -> Self {
+        self.straights = straights;
+        self
+    }
+
+    /// Probability of continuing a staircase of alternating turns when possible
+    pub fn diagonals(mut self, diagonals: f64) -> Self {
+        self.diagonals = diagonals;
+        self
+    }
+
+    pub fn generate(mut self) -> Result<Maze<R, C>> {
+        for (name, probability) in [
+            ("Loops", self.loops),
+            ("Straights", self.straights),
+            ("Diagonals", self.diagonals),
+        ] {
+            if !(0.0..=1.0).contains(&probability) {
+                bail!("{} probability {} is not from 0 to 1", name, probability);
+            }
+        }
+
+        let mut maze = Maze::<R, C>::new();
+
+        for x in 0..C {
+            for y in 0..R {
+                maze.set_cell_state(Cell { x, y }, CellState::all() - CellState::Visited);
+            }
+        }
+
+        let start = Cell { x: 0, y: 0 };
+        let goal = GoalArea::center::<R, C>();
+
+        maze.set_start_cell(start);
+        maze.set_goal_area(goal)?;
+
+        self.open_goal(&mut maze, goal);
+        self.carve(&mut maze, start);
+        self.open_goal_entrance(&mut maze, goal)?;
+        self.add_loops(&mut maze, start);
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        if let Some(diagnostic) = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            bail!("Generated maze is invalid: {}", diagnostic);
+        }
+
+        Ok(maze)
+    }
+
+    fn open_goal(&mut self, maze: &mut Maze<R, C>, goal: GoalArea) {
+        for x in goal.x..goal.x + goal.width {
+            for y in goal.y..goal.y + goal.height {
+                if x + 1 < goal.x + goal.width {
+                    maze.update_cell_state(Cell { x, y }, CellState::EastWall, false);
+                }
+
+                if y + 1 < goal.y + goal.height {
+                    maze.update_cell_state(Cell { x, y }, CellState::NorthWall, false);
+                }
+            }
+        }
+    }
+
+    fn carve(&mut self, maze: &mut Maze<R, C>, start: Cell<R, C>) {
+        let mut visited = [[false; R]; C];
+
+        for cell in maze.get_goal_cells() {
+            visited[cell.x][cell.y] = true;
+        }
+
+        visited[start.x][start.y] = true;
+
+        let mut stack = vec![Step {
+            cell: start,
+            direction: None,
+            turn: Turn::None,
+        }];
+
+        while let Some(step) = stack.last() {
+            let candidates: Vec<MazeOrientation> = MazeOrientation::cardinals()
+                .filter(|direction| {
+                    // The start cell is bounded on three sides with the exit to the north
+                    step.cell != start || *direction == MazeOrientation::North
+                })
+                .filter(|direction| {
+                    neighbour(maze, step.cell, *direction)
+                        .map(|cell| !visited[cell.x][cell.y])
+                        .unwrap_or(false)
+                })
+                .collect();
+
+            if candidates.is_empty() {
+                stack.pop();
+
+                continue;
+            }
+
+            let direction = self.choose_direction(step, &candidates);
+            let cell = step.cell;
+            let next = neighbour(maze, cell, direction).expect("Candidate should be in bounds");
+
+            let turn = match step.direction {
+                Some(previous) if quarter_turn(previous, RotationDirection::Right) == direction => {
+                    Turn::Right
+                }
+                Some(previous) if quarter_turn(previous, RotationDirection::Left) == direction => {
+                    Turn::Left
+                }
+                _ => Turn::None,
+            };
+
+            maze.update_cell_state(cell, wall(direction), false);
+            visited[next.x][next.y] = true;
+
+            stack.push(Step {
+                cell: next,
+                direction: Some(direction),
+                turn,
+            });
+        }
+    }
+
+    fn choose_direction(
+        &mut self,
+        step: &Step<R, C>,
+        candidates: &[MazeOrientation],
+    ) -> MazeOrientation {
+        if let Some(previous) = step.direction {
+            let zigzag = match step.turn {
+                Turn::Right => Some(quarter_turn(previous, RotationDirection::Left)),
+                Turn::Left => Some(quarter_turn(previous, RotationDirection::Right)),
+                Turn::None => None,
+            };
+
+            if let Some(zigzag) = zigzag {
+                if candidates.contains(&zigzag) && self.rng.gen_bool(self.diagonals) {
+                    return zigzag;
+                }
+            }
+
+            if candidates.contains(&previous) && self.rng.gen_bool(self.straights) {
+                return previous;
+            }
+        }
+
+        *candidates
+            .choose(&mut self.rng)
+            .expect("Candidates shouldn't be empty")
+    }
+
+    fn open_goal_entrance(&mut self, maze: &mut Maze<R, C>, goal: GoalArea) -> Result<()> {
+        let mut entrances = Vec::new();
+
+        for cell in maze.get_goal_cells() {
+            for direction in MazeOrientation::cardinals() {
+                if let Some(outside) = neighbour(maze, cell, direction) {
+                    if !maze.is_goal_cell(outside) {
+                        entrances.push((cell, wall(direction)));
+                    }
+                }
+            }
+        }
+
+        let Some((cell, wall)) = entrances.choose(&mut self.rng) else {
+            bail!("Goal area {:?} has no neighbouring cells", goal);
+        };
+
+        maze.update_cell_state(*cell, *wall, false);
+
+        Ok(())
+    }
+
+    fn add_loops(&mut self, maze: &mut Maze<R, C>, start: Cell<R, C>) {
+        let mut walls = Vec::new();
+
+        for x in 0..C {
+            for y in 0..R {
+                let cell = Cell { x, y };
+
+                for direction in [MazeOrientation::North, MazeOrientation::East] {
+                    let Some(other) = neighbour(maze, cell, direction) else {
+                        continue;
+                    };
+
+                    let wall = wall(direction);
+
+                    // Keep the start cell closed and the goal with a single entrance
+                    if maze.get_cell_state(cell).contains(wall)
+                        && cell != start
+                        && other != start
+                        && !maze.is_goal_cell(cell)
+                        && !maze.is_goal_cell(other)
+                    {
+                        walls.push((cell, wall));
+                    }
+                }
+            }
+        }
+
+        walls.shuffle(&mut self.rng);
+
+        let count = (walls.len() as f64 * self.loops) as usize;
+
+        for (cell, wall) in walls.into_iter().take(count) {
+            maze.update_cell_state(cell, wall, false);
+
+            // Competition mazes don't have free standing posts
+            let corners = [
+                (cell.x, cell.y),
+                (cell.x + 1, cell.y),
+                (cell.x, cell.y + 1),
+                (cell.x + 1, cell.y + 1),
+            ];
+
+            if corners
+                .into_iter()
+                .any(|(x, y)| maze.is_free_standing_post(x, y))
+            {
+                maze.update_cell_state(cell, wall, true);
+            }
+        }
+    }
+}
+
+fn wall(direction: MazeOrientation) -> CellState {
+    direction
+        .get_wall()
+        .expect("Carving only goes in cardinal directions")
+}
+
+fn quarter_turn(direction: MazeOrientation, rotation: RotationDirection) -> MazeOrientation {
+    direction.turned(rotation, 2)
+}
+
+fn neighbour<const R: usize, const C: usize>(
+    maze: &Maze<R, C>,
+    cell: Cell<R, C>,
+    direction: MazeOrientation,
+) -> Option<Cell<R, C>> {
+    maze.get_neighbour(cell, wall(direction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_maze() {
+        let first = MazeGenerator::<16, 16>::new(7).generate().unwrap();
+        let second = MazeGenerator::<16, 16>::new(7).generate().unwrap();
+
+        for x in 0..16 {
+            for y in 0..16 {
+                let cell = Cell { x, y };
+
+                assert_eq!(first.get_cell_state(cell), second.get_cell_state(cell));
+            }
+        }
+
+        assert_eq!(first.get_start_cell(), second.get_start_cell());
+        assert_eq!(first.get_goal_cells(), second.get_goal_cells());
+    }
+
+    #[test]
+    fn generated_mazes_pass_the_validator() {
+        for seed in 0..20 {
+            let maze = MazeGenerator::<16, 16>::new(seed)
+                .loops(0.2)
+                .generate()
+                .unwrap();
+
+            let diagnostics = MazeValidator::new(&maze).validate();
+
+            assert!(diagnostics.is_empty(), "seed {}: {:?}", seed, diagnostics);
+        }
+    }
+
+    #[test]
+    fn invalid_probabilities_are_rejected() {
+        for probability in [f64::NAN, -0.1, 1.5] {
+            assert!(MazeGenerator::<16, 16>::new(0)
+                .loops(probability)
+                .generate()
+                .is_err());
+            assert!(MazeGenerator::<16, 16>::new(0)
+                .straights(probability)
+                .generate()
+                .is_err());
+            assert!(MazeGenerator::<16, 16>::new(0)
+                .diagonals(probability)
+                .generate()
+                .is_err());
+        }
+    }
+}
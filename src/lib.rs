@@ -0,0 +1,32 @@
+pub mod battery;
+mod browser;
+mod communication;
+mod context;
+mod distance_sensors;
+mod editor;
+mod engine;
+pub mod environment;
+pub mod estimator;
+pub mod generator;
+pub mod maze;
+pub mod mazefile;
+mod outputs;
+mod overlay;
+mod panel;
+mod placement;
+pub mod position;
+pub mod run;
+pub mod runner;
+pub mod scoring;
+mod search;
+pub mod shared_maze;
+pub mod simulator;
+pub mod solver;
+pub mod trajectory;
+pub mod validation;
+mod velocity;
+
+pub use maze_simulator_protocol as protocol;
+
+pub const COLS: usize = 16;
+pub const ROWS: usize = 16;
@@ -1,38 +1,373 @@
-mod communication;
-mod context;
-mod distance_sensors;
-mod engine;
-mod environment;
-mod maze;
-mod mazefile;
-mod panel;
-mod position;
-mod runner;
-mod simulator;
-mod velocity;
-
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
-
-use mazefile::Mazefile;
-use simulator::MazeSimulator;
-
-pub const COLS: usize = 16;
-pub const ROWS: usize = 16;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use maze_simulator::{
+    battery::BatteryModel,
+    estimator::{FastRunProfile, RunEstimator},
+    generator::MazeGenerator,
+    maze::{Cell, GoalArea},
+    mazefile::{Mazefile, MazefileFormat},
+    scoring::ScoringRules,
+    simulator::MazeSimulator,
+    solver::{MazeSolver, PathMetric},
+    validation::{MazeValidator, Severity},
+    COLS, ROWS,
+};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to mazefile with the map
-    #[arg(short, long)]
-    mazefile: PathBuf,
+    #[arg(short, long, required = true)]
+    mazefile: Option<PathBuf>,
+
+    /// Directory listed by the in-window maze browser [default: mazefile directory]
+    #[arg(long)]
+    maze_dir: Option<PathBuf>,
+
+    /// Start cell as 'x,y', overrides the mazefile marker
+    #[arg(long)]
+    start: Option<Cell<ROWS, COLS>>,
+
+    /// Goal area as 'x,y,width,height', overrides the mazefile markers [default: maze center]
+    #[arg(long)]
+    goal: Option<GoalArea>,
+
+    /// Return the runner to the start cell after it crashes into a wall
+    #[arg(long)]
+    reset_on_crash: bool,
+
+    /// Competition rules used to score the runs
+    #[arg(long, value_enum, default_value_t)]
+    rules: ScoringRules,
+
+    /// Total maze time in seconds, defaults to the limit of the selected rules
+    #[arg(long)]
+    maze_time: Option<u64>,
+
+    /// Number of LEDs on the runner [default: 4]
+    #[arg(long)]
+    leds: Option<usize>,
+
+    /// Battery voltage in volts below which the motors are switched off
+    #[arg(long, default_value_t = BatteryModel::default().cutoff_voltage)]
+    battery_cutoff: f64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check mazefiles for errors and rule violations
+    Validate {
+        /// Mazefile or directory with mazefiles
+        path: PathBuf,
+    },
+    /// Convert a mazefile to a different format
+    Convert {
+        /// Mazefile to convert
+        input: PathBuf,
+
+        /// Path of the converted mazefile
+        output: PathBuf,
+
+        /// Format of the input, detected from the extension or content by default
+        #[arg(long, value_enum)]
+        from: Option<MazefileFormat>,
+
+        /// Format of the output, detected from the extension by default
+        #[arg(long, value_enum)]
+        to: Option<MazefileFormat>,
+    },
+    /// Generate a random competition style maze
+    Generate {
+        /// Path of the generated mazefile
+        output: PathBuf,
+
+        /// Seed of the generator, random by default
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Fraction of inner walls removed to create loops
+        #[arg(long, default_value_t = 0.05)]
+        loops: f64,
+
+        /// Probability of carving long straight corridors
+        #[arg(long, default_value_t = 0.5)]
+        straights: f64,
+
+        /// Probability of carving diagonal staircases
+        #[arg(long, default_value_t = 0.3)]
+        diagonals: f64,
+
+        /// Format of the output, detected from the extension by default
+        #[arg(long, value_enum)]
+        format: Option<MazefileFormat>,
+    },
+    /// Print the optimal path from the start to the goal for every metric
+    Solve {
+        /// Mazefile to solve
+        path: PathBuf,
+    },
+    /// Estimate the best fast run time with diagonals and smooth turns
+    Estimate {
+        /// Mazefile to estimate
+        path: PathBuf,
+
+        /// Top speed on orthogonal straights [mm/s]
+        #[arg(long, default_value_t = FastRunProfile::default().max_speed)]
+        max_speed: f64,
+
+        /// Top speed on diagonal straights [mm/s]
+        #[arg(long, default_value_t = FastRunProfile::default().max_diagonal_speed)]
+        max_diagonal_speed: f64,
+
+        /// Acceleration and deceleration on straights [mm/s^2]
+        #[arg(long, default_value_t = FastRunProfile::default().acceleration)]
+        acceleration: f64,
+
+        /// Centripetal acceleration limiting the speed in turns [mm/s^2]
+        #[arg(long, default_value_t = FastRunProfile::default().lateral_acceleration)]
+        lateral_acceleration: f64,
+
+        /// Radius of turns between orthogonal and diagonal straights [mm]
+        #[arg(long, default_value_t = FastRunProfile::default().radius_45)]
+        radius_45: f64,
+
+        /// Radius of turns between diagonal straights [mm]
+        #[arg(long, default_value_t = FastRunProfile::default().radius_90)]
+        radius_90: f64,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let maze = Mazefile::<ROWS, COLS>::load(args.mazefile)?.parse()?;
+    match args.command {
+        Some(Command::Validate { path }) => validate(&path),
+        Some(Command::Convert {
+            input,
+            output,
+            from,
+            to,
+        }) => convert(input, output, from, to),
+        Some(Command::Solve { path }) => solve(path),
+        Some(Command::Estimate {
+            path,
+            max_speed,
+            max_diagonal_speed,
+            acceleration,
+            lateral_acceleration,
+            radius_45,
+            radius_90,
+        }) => estimate(
+            path,
+            FastRunProfile {
+                max_speed,
+                max_diagonal_speed,
+                acceleration,
+                lateral_acceleration,
+                radius_45,
+                radius_90,
+            },
+        ),
+        Some(Command::Generate {
+            output,
+            seed,
+            loops,
+            straights,
+            diagonals,
+            format,
+        }) => {
+            let seed = seed.unwrap_or_else(rand::random);
+
+            let maze = MazeGenerator::<ROWS, COLS>::new(seed)
+                .loops(loops)
+                .straights(straights)
+                .diagonals(diagonals)
+                .generate()?;
+
+            let format = format
+                .or_else(|| MazefileFormat::from_extension(&output))
+                .unwrap_or(MazefileFormat::Ascii);
+
+            Mazefile::from_maze(&maze, format)?.save(output)?;
+
+            println!("Generated maze with seed {}", seed);
+
+            Ok(())
+        }
+        None => run(args),
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let mazefile = args.mazefile.context("Mazefile is required")?;
+
+    let mut maze = Mazefile::<ROWS, COLS>::load(mazefile.clone())?.parse()?;
+
+    maze.infer_missing_markers(GoalArea::center::<ROWS, COLS>())?;
+
+    let mut builder = MazeSimulator::builder(maze)
+        .mazefile(mazefile)
+        .reset_on_crash(args.reset_on_crash)
+        .rules(args.rules)
+        .battery(BatteryModel {
+            cutoff_voltage: args.battery_cutoff,
+            ..Default::default()
+        });
+
+    if let Some(maze_dir) = args.maze_dir {
+        builder = builder.maze_directory(maze_dir);
+    }
+
+    if let Some(maze_time) = args.maze_time {
+        builder = builder.maze_time_limit(Duration::from_secs(maze_time));
+    }
+
+    if let Some(leds) = args.leds {
+        builder = builder.led_count(leds);
+    }
+
+    // Kept for mazes loaded from the window too
+    if let Some(start) = args.start {
+        builder = builder.start_cell(start);
+    }
+
+    if let Some(goal) = args.goal {
+        builder = builder.goal_area(goal);
+    }
+
+    builder.build()?.run()
+}
+
+fn solve(path: PathBuf) -> Result<()> {
+    let mut maze = Mazefile::<ROWS, COLS>::load(path)?.parse()?;
+
+    maze.infer_missing_markers(GoalArea::center::<ROWS, COLS>())?;
+
+    let solver = MazeSolver::new(&maze);
+
+    for metric in PathMetric::value_variants() {
+        let Some(solution) = solver.solve(*metric) else {
+            bail!("Goal is not reachable from the start cell");
+        };
+
+        println!(
+            "{:?}: {} cells, {} turns, {:.3} s",
+            metric,
+            solution.get_cell_count(),
+            solution.turns,
+            solution.time.as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+fn estimate(path: PathBuf, profile: FastRunProfile) -> Result<()> {
+    let mut maze = Mazefile::<ROWS, COLS>::load(path)?.parse()?;
+
+    maze.infer_missing_markers(GoalArea::center::<ROWS, COLS>())?;
+
+    let Some(estimate) = RunEstimator::new(&maze).profile(profile).estimate() else {
+        bail!("Goal is not reachable from the start cell");
+    };
+
+    let moves: Vec<String> = estimate.moves.iter().map(|m| m.to_string()).collect();
+
+    println!("{}", moves.join(" "));
+    println!(
+        "{:.3} s, {} turns, {} diagonals",
+        estimate.time.as_secs_f64(),
+        estimate.get_turn_count(),
+        estimate.get_diagonal_count()
+    );
+
+    Ok(())
+}
+
+fn validate(path: &Path) -> Result<()> {
+    let paths = if path.is_dir() {
+        let mut paths = std::fs::read_dir(path)
+            .context("Couldn't read mazefile directory")?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Couldn't read mazefile directory")?;
+
+        paths.retain(|path| path.is_file());
+        paths.sort();
+
+        paths
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut failed = 0;
+
+    for path in paths {
+        let maze = match Mazefile::<ROWS, COLS>::load(path.clone()).and_then(|m| m.parse()) {
+            Ok(maze) => maze,
+            Err(e) => {
+                println!("{}: error: {:#}", path.display(), e);
+                failed += 1;
+
+                continue;
+            }
+        };
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        if diagnostics.is_empty() {
+            println!("{}: ok", path.display());
+        }
+
+        for diagnostic in &diagnostics {
+            println!("{}: {}", path.display(), diagnostic);
+        }
+
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} mazefile(s) failed validation", failed);
+    }
+
+    Ok(())
+}
+
+fn convert(
+    input: PathBuf,
+    output: PathBuf,
+    from: Option<MazefileFormat>,
+    to: Option<MazefileFormat>,
+) -> Result<()> {
+    let to = match to.or_else(|| MazefileFormat::from_extension(&output)) {
+        Some(to) => to,
+        None => bail!("Couldn't determine the output format, use --to"),
+    };
+
+    let mut mazefile = Mazefile::<ROWS, COLS>::load(input)?;
+
+    if let Some(from) = from {
+        mazefile = mazefile.with_format(from);
+    }
+
+    let maze = mazefile.parse()?;
+
+    if !to.stores_markers() {
+        println!("Start and goal cells are not stored in the {:?} format", to);
+    }
 
-    MazeSimulator::run(maze)
+    Mazefile::from_maze(&maze, to)?.save(output)
 }
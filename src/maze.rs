@@ -1,28 +1,17 @@
-use anyhow::{anyhow, bail, Context, Result};
-use bitflags::bitflags;
+use anyhow::{bail, Context, Result};
 use pix_engine::{prelude::Color, rect, state::PixState};
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 use crate::{
     engine::Render,
     simulator::{CELL_SIZE_VIS, WALL_LENGTH_VIS, WALL_WIDTH_VIS},
 };
 
-pub struct Posts<const R: usize, const C: usize>;
+pub use maze_simulator_protocol::CellState;
 
-bitflags! {
-    #[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    #[serde(transparent)]
-    pub struct CellState: u8 {
-        const NorthWall = 0b00000001;
-        const EastWall = 0b00000010;
-        const SouthWall = 0b00000100;
-        const WestWall = 0b00001000;
-        const Visited = 0b00010000;
-    }
-}
+pub struct Posts<const R: usize, const C: usize>;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Cell<const R: usize, const C: usize> {
     pub x: usize,
     pub y: usize,
@@ -38,61 +27,128 @@ impl<const R: usize, const C: usize> Cell<R, C> {
     }
 }
 
+impl<const R: usize, const C: usize> FromStr for Cell<R, C> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let values = parse_coordinates(s)?;
+
+        let [x, y] = values[..] else {
+            bail!("Expected 'x,y', found '{}'", s);
+        };
+
+        Self::new(x, y)
+    }
+}
+
+/// Rectangle of goal cells, `x` and `y` being its south-west corner
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GoalArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GoalArea {
+    /// Classic goal in the center of the maze, 2x2 cells for even sizes and one cell otherwise
+    pub fn center<const R: usize, const C: usize>() -> Self {
+        let (x, width) = if C % 2 == 0 {
+            (C / 2 - 1, 2)
+        } else {
+            (C / 2, 1)
+        };
+        let (y, height) = if R % 2 == 0 {
+            (R / 2 - 1, 2)
+        } else {
+            (R / 2, 1)
+        };
+
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl FromStr for GoalArea {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let values = parse_coordinates(s)?;
+
+        let [x, y, width, height] = values[..] else {
+            bail!("Expected 'x,y,width,height', found '{}'", s);
+        };
+
+        if width == 0 || height == 0 {
+            bail!("Goal area can't be empty");
+        }
+
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+fn parse_coordinates(s: &str) -> Result<Vec<usize>> {
+    s.split(',')
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid coordinate '{}'", value))
+        })
+        .collect()
+}
+
 #[derive(Copy, Clone)]
 pub struct Goal<const R: usize, const C: usize> {
-    g0: Option<Cell<R, C>>,
-    g1: Option<Cell<R, C>>,
-    g2: Option<Cell<R, C>>,
-    g3: Option<Cell<R, C>>,
+    cells: [[bool; R]; C],
+}
+
+impl<const R: usize, const C: usize> Default for Goal<R, C> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<const R: usize, const C: usize> Goal<R, C> {
     pub fn new() -> Self {
         Self {
-            g0: None,
-            g1: None,
-            g2: None,
-            g3: None,
+            cells: [[false; R]; C],
         }
     }
 
-    pub fn set(&mut self, cell: Cell<R, C>) -> Result<()> {
-        if self.g0.is_none() {
-            self.g0 = Some(cell);
-
-            Ok(())
-        } else if self.g1.is_none() {
-            self.g1 = Some(cell);
+    pub fn set(&mut self, cell: Cell<R, C>) {
+        self.cells[cell.x][cell.y] = true;
+    }
 
-            Ok(())
-        } else if self.g2.is_none() {
-            self.g2 = Some(cell);
+    pub fn clear(&mut self, cell: Cell<R, C>) {
+        self.cells[cell.x][cell.y] = false;
+    }
 
-            Ok(())
-        } else if self.g3.is_none() {
-            self.g3 = Some(cell);
+    pub fn get_cells(&self) -> Vec<Cell<R, C>> {
+        let mut cells = Vec::new();
 
-            Ok(())
-        } else {
-            Err(anyhow!("All 4 goal cells are set"))
+        for x in 0..C {
+            for y in 0..R {
+                if self.cells[x][y] {
+                    cells.push(Cell { x, y });
+                }
+            }
         }
+
+        cells
     }
 
-    #[allow(dead_code)]
     pub fn is_target(&self, cell: Cell<R, C>) -> bool {
-        let cell = Some(cell);
-
-        if self.g0 == cell {
-            true
-        } else if self.g1 == cell {
-            true
-        } else if self.g2 == cell {
-            true
-        } else if self.g3 == cell {
-            true
-        } else {
-            false
-        }
+        self.cells[cell.x][cell.y]
     }
 }
 
@@ -100,7 +156,13 @@ impl<const R: usize, const C: usize> Goal<R, C> {
 pub struct Maze<const R: usize, const C: usize> {
     map: [[CellState; R]; C],
     goal: Goal<R, C>,
-    start: Cell<R, C>,
+    start: Option<Cell<R, C>>,
+}
+
+impl<const R: usize, const C: usize> Default for Maze<R, C> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<const R: usize, const C: usize> Maze<R, C> {
@@ -108,7 +170,7 @@ impl<const R: usize, const C: usize> Maze<R, C> {
         Self {
             map: [[CellState::default(); R]; C],
             goal: Goal::new(),
-            start: Cell::new(0, 0).expect("Constructor should succeed"),
+            start: None,
         }
     }
 
@@ -116,6 +178,10 @@ impl<const R: usize, const C: usize> Maze<R, C> {
         self.map[cell.x][cell.y]
     }
 
+    pub fn set_cell_state(&mut self, cell: Cell<R, C>, state: CellState) {
+        self.map[cell.x][cell.y] = state;
+    }
+
     pub fn update_cell_state(&mut self, cell: Cell<R, C>, state: CellState, value: bool) {
         self.map[cell.x][cell.y].set(state, value);
 
@@ -144,16 +210,130 @@ impl<const R: usize, const C: usize> Maze<R, C> {
         }
     }
 
-    pub fn set_goal_cell(&mut self, cell: Cell<R, C>) -> Result<()> {
+    pub fn set_goal_cell(&mut self, cell: Cell<R, C>) {
         self.goal.set(cell)
     }
 
+    pub fn clear_goal_cell(&mut self, cell: Cell<R, C>) {
+        self.goal.clear(cell)
+    }
+
+    /// Replaces the goal, which is left as it was if the area doesn't fit in the maze
+    pub fn set_goal_area(&mut self, area: GoalArea) -> Result<()> {
+        let right = area.x.checked_add(area.width).filter(|right| *right <= C);
+        let top = area.y.checked_add(area.height).filter(|top| *top <= R);
+
+        let (Some(right), Some(top)) = (right, top) else {
+            bail!("Goal area {:?} out of bounds", area);
+        };
+
+        self.goal = Goal::new();
+
+        for y in area.y..top {
+            for x in area.x..right {
+                self.set_goal_cell(Cell { x, y });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_accessible_neighbours(&self, cell: Cell<R, C>) -> Vec<Cell<R, C>> {
+        let cell_state = self.get_cell_state(cell);
+
+        [
+            CellState::NorthWall,
+            CellState::EastWall,
+            CellState::SouthWall,
+            CellState::WestWall,
+        ]
+        .into_iter()
+        .filter(|wall| !cell_state.contains(*wall))
+        .filter_map(|wall| self.get_neighbour(cell, wall))
+        .collect()
+    }
+
+    /// Cell on the other side of a wall, whether the wall is there or not
+    pub fn get_neighbour(&self, cell: Cell<R, C>, wall: CellState) -> Option<Cell<R, C>> {
+        let (x, y) = if wall == CellState::NorthWall {
+            (cell.x, cell.y + 1)
+        } else if wall == CellState::EastWall {
+            (cell.x + 1, cell.y)
+        } else if wall == CellState::SouthWall {
+            (cell.x, cell.y.checked_sub(1)?)
+        } else if wall == CellState::WestWall {
+            (cell.x.checked_sub(1)?, cell.y)
+        } else {
+            return None;
+        };
+
+        Cell::new(x, y).ok()
+    }
+
     pub fn set_start_cell(&mut self, cell: Cell<R, C>) {
-        self.start = cell;
+        self.start = Some(cell);
+    }
+
+    pub fn has_start_cell(&self) -> bool {
+        self.start.is_some()
     }
 
     pub fn get_start_cell(&self) -> Cell<R, C> {
-        self.start
+        self.start.unwrap_or(Cell { x: 0, y: 0 })
+    }
+
+    /// Start cell used when none is marked, the first corner bounded on three sides
+    pub fn infer_start_cell(&self) -> Cell<R, C> {
+        let corners = [(0, 0), (C - 1, 0), (0, R - 1), (C - 1, R - 1)];
+
+        corners
+            .into_iter()
+            .map(|(x, y)| Cell { x, y })
+            .find(|cell| self.get_accessible_neighbours(*cell).len() == 1)
+            .unwrap_or(Cell { x: 0, y: 0 })
+    }
+
+    /// Applies the standard competition markers when the maze doesn't specify them
+    pub fn infer_missing_markers(&mut self, default_goal: GoalArea) -> Result<()> {
+        if !self.has_start_cell() {
+            self.set_start_cell(self.infer_start_cell());
+        }
+
+        if self.get_goal_cells().is_empty() {
+            self.set_goal_area(default_goal)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_goal_cells(&self) -> Vec<Cell<R, C>> {
+        self.goal.get_cells()
+    }
+
+    pub fn is_goal_cell(&self, cell: Cell<R, C>) -> bool {
+        self.goal.is_target(cell)
+    }
+
+    /// Inner post at the south-west corner of (x, y) without walls attached, the one in the
+    /// middle of the goal is allowed to stand free
+    pub fn is_free_standing_post(&self, x: usize, y: usize) -> bool {
+        if x == 0 || y == 0 || x >= C || y >= R {
+            return false;
+        }
+
+        let has_wall =
+            |x: usize, y: usize, wall: CellState| self.get_cell_state(Cell { x, y }).contains(wall);
+
+        let attached = has_wall(x, y, CellState::WestWall)
+            || has_wall(x, y - 1, CellState::WestWall)
+            || has_wall(x, y, CellState::SouthWall)
+            || has_wall(x - 1, y, CellState::SouthWall);
+
+        let surrounded_by_goal = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)]
+            .iter()
+            .all(|&(x, y)| self.is_goal_cell(Cell { x, y }));
+
+        !attached && !surrounded_by_goal
     }
 }
 
@@ -281,3 +461,41 @@ impl<const R: usize, const C: usize> Render for Posts<R, C> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: usize, y: usize, width: usize, height: usize) -> GoalArea {
+        GoalArea {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn goal_area_is_set() {
+        let mut maze = Maze::<4, 4>::new();
+
+        maze.set_goal_area(area(2, 1, 2, 3)).unwrap();
+
+        assert_eq!(maze.get_goal_cells().len(), 6);
+        assert!(maze.is_goal_cell(Cell { x: 3, y: 3 }));
+        assert!(!maze.is_goal_cell(Cell { x: 1, y: 1 }));
+    }
+
+    #[test]
+    fn goal_area_out_of_bounds_keeps_the_goal() {
+        let mut maze = Maze::<4, 4>::new();
+
+        maze.set_goal_area(area(1, 1, 1, 1)).unwrap();
+
+        assert!(maze.set_goal_area(area(3, 0, 2, 1)).is_err());
+        assert!(maze.set_goal_area(area(0, 1, 1, usize::MAX)).is_err());
+        assert!(maze.set_goal_area(area(usize::MAX, 0, 1, 1)).is_err());
+
+        assert_eq!(maze.get_goal_cells(), vec![Cell { x: 1, y: 1 }]);
+    }
+}
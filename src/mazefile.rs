@@ -1,79 +1,821 @@
 use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 use crate::maze::{Cell, CellState, Maze};
 
+const POST: u8 = b'o';
+const HORIZONTAL_WALL: u8 = b'-';
+const VERTICAL_WALL: u8 = b'|';
+const GOAL: u8 = b'G';
+const START: u8 = b'S';
+const EMPTY: u8 = b' ';
+
+#[derive(Debug)]
+pub struct MazefileError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl MazefileError {
+    fn new(line: usize, column: usize, message: String) -> Self {
+        Self {
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for MazefileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for MazefileError {}
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum MazefileFormat {
+    /// Text drawing with `o` posts, `-` and `|` walls, `S` start and `G` goal markers
+    Ascii,
+    /// One byte per cell with the wall bitmask, cells ordered column by column (`.maz`)
+    Binary,
+    /// One line per cell with its coordinates followed by either the wall bitmask or
+    /// separate north, east, south and west flags (`.num`)
+    Numeric,
+    /// JSON object with the maze size, start, goal and wall bitmasks
+    Json,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonMaze {
+    width: usize,
+    height: usize,
+    start: Option<(usize, usize)>,
+    goal: Vec<(usize, usize)>,
+    /// Wall bitmask of every cell, rows ordered from south to north
+    walls: Vec<Vec<u8>>,
+}
+
+impl MazefileFormat {
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "txt" => Some(MazefileFormat::Ascii),
+            "maz" => Some(MazefileFormat::Binary),
+            "num" => Some(MazefileFormat::Numeric),
+            "json" => Some(MazefileFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn stores_markers(&self) -> bool {
+        matches!(self, MazefileFormat::Ascii | MazefileFormat::Json)
+    }
+
+    pub fn detect(input: &[u8]) -> Self {
+        let first_character = input.iter().find(|c| !c.is_ascii_whitespace());
+
+        if first_character == Some(&POST) {
+            MazefileFormat::Ascii
+        } else if first_character == Some(&b'{') {
+            MazefileFormat::Json
+        } else if input
+            .iter()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_whitespace())
+        {
+            MazefileFormat::Numeric
+        } else {
+            MazefileFormat::Binary
+        }
+    }
+}
+
 pub struct Mazefile<const R: usize, const C: usize> {
-    input: String,
+    input: Vec<u8>,
+    format: MazefileFormat,
+}
+
+struct Line<'a> {
+    number: usize,
+    content: &'a [u8],
+}
+
+impl<'a> Line<'a> {
+    fn at(&self, index: usize) -> Option<u8> {
+        self.content.get(index).copied()
+    }
+
+    fn error(&self, index: usize, message: String) -> MazefileError {
+        MazefileError::new(self.number, index + 1, message)
+    }
+
+    fn expect(&self, index: usize, expected: &[u8]) -> Result<u8, MazefileError> {
+        // Trailing whitespace is trimmed, so a missing character is an empty field
+        let found = self.at(index).unwrap_or(EMPTY);
+
+        if expected.contains(&found) {
+            return Ok(found);
+        }
+
+        let expected = expected
+            .iter()
+            .map(|c| format!("'{}'", *c as char))
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        let found = match self.at(index) {
+            Some(c) => format!("'{}'", c as char),
+            None => "end of line".to_string(),
+        };
+
+        Err(self.error(index, format!("expected {expected}, found {found}")))
+    }
 }
 
 impl<const R: usize, const C: usize> Mazefile<R, C> {
     pub fn load(path: PathBuf) -> Result<Self> {
-        let mut file = File::open(path).context("Couldn't open mazefile")?;
+        let mut file = File::open(&path).context("Couldn't open mazefile")?;
 
-        let mut input = String::new();
-        file.read_to_string(&mut input)
+        let mut input = Vec::new();
+        file.read_to_end(&mut input)
             .context("Couldn't read mazefile")?;
 
-        Ok(Self { input })
+        let format =
+            MazefileFormat::from_extension(&path).unwrap_or_else(|| MazefileFormat::detect(&input));
+
+        Ok(Self { input, format })
+    }
+
+    pub fn from_maze(maze: &Maze<R, C>, format: MazefileFormat) -> Result<Self> {
+        let input = match format {
+            MazefileFormat::Ascii => Self::write_ascii(maze),
+            MazefileFormat::Binary => Self::write_binary(maze),
+            MazefileFormat::Numeric => Self::write_numeric(maze),
+            MazefileFormat::Json => Self::write_json(maze)?,
+        };
+
+        Ok(Self { input, format })
+    }
+
+    pub fn save(&self, path: PathBuf) -> Result<()> {
+        let mut file = File::create(path).context("Couldn't create mazefile")?;
+
+        file.write_all(&self.input)
+            .context("Couldn't write mazefile")
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.input
+    }
+
+    pub fn from_bytes(input: Vec<u8>) -> Self {
+        let format = MazefileFormat::detect(&input);
+
+        Self { input, format }
+    }
+
+    pub fn with_format(mut self, format: MazefileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn get_format(&self) -> MazefileFormat {
+        self.format
     }
 
     pub fn parse(self) -> Result<Maze<R, C>> {
-        let lines: Vec<&str> = self.input.lines().collect();
+        // Whitespace bytes are ordinary wall bitmasks in binary mazefiles
+        let empty = match self.format {
+            MazefileFormat::Binary => self.input.is_empty(),
+            _ => self.input.iter().all(|c| c.is_ascii_whitespace()),
+        };
 
-        if lines.is_empty() {
-            bail!("No valid input!");
+        if empty {
+            bail!("Mazefile is empty");
+        }
+
+        match self.format {
+            MazefileFormat::Ascii => Ok(self.parse_ascii()?),
+            MazefileFormat::Binary => self.parse_binary(),
+            MazefileFormat::Numeric => Ok(self.parse_numeric()?),
+            MazefileFormat::Json => self.parse_json(),
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = Line<'_>> {
+        self.input
+            .split(|c| *c == b'\n')
+            .enumerate()
+            .map(|(index, content)| Line {
+                number: index + 1,
+                content: content.trim_ascii_end(),
+            })
+    }
+
+    fn parse_binary(&self) -> Result<Maze<R, C>> {
+        if self.input.len() != R * C {
+            bail!(
+                "Binary mazefile has {} bytes, expected {} for a {}x{} maze",
+                self.input.len(),
+                R * C,
+                C,
+                R
+            );
         }
 
         let mut maze = Maze::<R, C>::new();
 
-        let mut line_index = 0;
+        for (index, value) in self.input.iter().enumerate() {
+            let cell = Cell::new(index / R, index % R).expect("Coordinates should be in bounds");
+
+            maze.set_cell_state(cell, Self::walls_from_bitmask(*value));
+        }
+
+        Ok(maze)
+    }
+
+    fn parse_numeric(&self) -> Result<Maze<R, C>, MazefileError> {
+        let mut maze = Maze::<R, C>::new();
+
+        for line in self.lines() {
+            let fields = Self::numeric_fields(&line)?;
+
+            let (x, y, walls) = match fields.as_slice() {
+                [] => continue,
+                [x, y, mask] => {
+                    let bitmask = u8::try_from(mask.1).map_err(|_| {
+                        line.error(
+                            mask.0,
+                            format!("wall bitmask {} is larger than 255", mask.1),
+                        )
+                    })?;
+
+                    (*x, *y, Self::walls_from_bitmask(bitmask))
+                }
+                [x, y, north, east, south, west] => {
+                    let mut walls = CellState::empty();
+
+                    walls.set(CellState::NorthWall, north.1 != 0);
+                    walls.set(CellState::EastWall, east.1 != 0);
+                    walls.set(CellState::SouthWall, south.1 != 0);
+                    walls.set(CellState::WestWall, west.1 != 0);
+
+                    (*x, *y, walls)
+                }
+                _ => {
+                    return Err(line.error(
+                        0,
+                        format!(
+                            "expected 'x y walls' or 'x y north east south west', found {} fields",
+                            fields.len()
+                        ),
+                    ))
+                }
+            };
+
+            let cell = Cell::new(x.1, y.1).map_err(|_| {
+                line.error(
+                    x.0,
+                    format!("cell ({}, {}) is outside of a {}x{} maze", x.1, y.1, C, R),
+                )
+            })?;
+
+            maze.set_cell_state(cell, walls);
+        }
+
+        Ok(maze)
+    }
+
+    fn numeric_fields(line: &Line) -> Result<Vec<(usize, usize)>, MazefileError> {
+        let mut fields = Vec::new();
+        let mut index = 0;
+
+        while index < line.content.len() {
+            if line.content[index].is_ascii_whitespace() {
+                index += 1;
+
+                continue;
+            }
+
+            let start = index;
+
+            while index < line.content.len() && !line.content[index].is_ascii_whitespace() {
+                index += 1;
+            }
+
+            let value = std::str::from_utf8(&line.content[start..index])
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| line.error(start, "expected a number".to_string()))?;
+
+            fields.push((start, value));
+        }
+
+        Ok(fields)
+    }
+
+    fn parse_json(&self) -> Result<Maze<R, C>> {
+        let json: JsonMaze =
+            serde_json::from_slice(&self.input).context("Couldn't parse JSON mazefile")?;
+
+        if json.width != C || json.height != R {
+            bail!(
+                "JSON mazefile describes a {}x{} maze, expected {}x{}",
+                json.width,
+                json.height,
+                C,
+                R
+            );
+        }
+
+        if json.walls.len() != R || json.walls.iter().any(|row| row.len() != C) {
+            bail!("JSON mazefile walls must have {} rows of {} cells", R, C);
+        }
+
+        let mut maze = Maze::<R, C>::new();
+
+        for (y, row) in json.walls.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                maze.set_cell_state(Cell::new(x, y)?, Self::walls_from_bitmask(*value));
+            }
+        }
+
+        if let Some((x, y)) = json.start {
+            maze.set_start_cell(Cell::new(x, y).context("Start cell out of bounds")?);
+        }
+
+        for (x, y) in json.goal {
+            maze.set_goal_cell(Cell::new(x, y).context("Goal cell out of bounds")?);
+        }
+
+        Ok(maze)
+    }
+
+    fn write_ascii(maze: &Maze<R, C>) -> Vec<u8> {
+        let mut output = String::new();
+
+        let wall_line = |output: &mut String, y: usize, wall: CellState| {
+            for x in 0..C {
+                output.push(POST as char);
+
+                if maze.get_cell_state(Cell { x, y }).contains(wall) {
+                    output.push_str("---");
+                } else {
+                    output.push_str("   ");
+                }
+            }
+
+            output.push(POST as char);
+            output.push('\n');
+        };
+
+        for row in (0..R).rev() {
+            wall_line(&mut output, row, CellState::NorthWall);
+
+            for x in 0..C {
+                let cell = Cell { x, y: row };
+                let cell_state = maze.get_cell_state(cell);
+
+                output.push(if cell_state.contains(CellState::WestWall) {
+                    VERTICAL_WALL as char
+                } else {
+                    EMPTY as char
+                });
+
+                output.push(' ');
+
+                output.push(if maze.has_start_cell() && maze.get_start_cell() == cell {
+                    START as char
+                } else if maze.is_goal_cell(cell) {
+                    GOAL as char
+                } else {
+                    EMPTY as char
+                });
+
+                output.push(' ');
+            }
+
+            output.push(
+                if maze
+                    .get_cell_state(Cell { x: C - 1, y: row })
+                    .contains(CellState::EastWall)
+                {
+                    VERTICAL_WALL as char
+                } else {
+                    EMPTY as char
+                },
+            );
+            output.push('\n');
+        }
+
+        wall_line(&mut output, 0, CellState::SouthWall);
+
+        output.into_bytes()
+    }
+
+    fn write_binary(maze: &Maze<R, C>) -> Vec<u8> {
+        (0..R * C)
+            .map(|index| {
+                Self::walls_to_bitmask(
+                    maze,
+                    Cell {
+                        x: index / R,
+                        y: index % R,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn write_numeric(maze: &Maze<R, C>) -> Vec<u8> {
+        let mut output = String::new();
+
+        for x in 0..C {
+            for y in 0..R {
+                let cell_state = maze.get_cell_state(Cell { x, y });
+
+                output.push_str(&format!(
+                    "{} {} {} {} {} {}\n",
+                    x,
+                    y,
+                    cell_state.contains(CellState::NorthWall) as u8,
+                    cell_state.contains(CellState::EastWall) as u8,
+                    cell_state.contains(CellState::SouthWall) as u8,
+                    cell_state.contains(CellState::WestWall) as u8,
+                ));
+            }
+        }
+
+        output.into_bytes()
+    }
+
+    fn write_json(maze: &Maze<R, C>) -> Result<Vec<u8>> {
+        let json = JsonMaze {
+            width: C,
+            height: R,
+            start: maze
+                .has_start_cell()
+                .then(|| maze.get_start_cell())
+                .map(|cell| (cell.x, cell.y)),
+            goal: maze
+                .get_goal_cells()
+                .iter()
+                .map(|cell| (cell.x, cell.y))
+                .collect(),
+            walls: (0..R)
+                .map(|y| {
+                    (0..C)
+                        .map(|x| Self::walls_to_bitmask(maze, Cell { x, y }))
+                        .collect()
+                })
+                .collect(),
+        };
+
+        serde_json::to_vec_pretty(&json).context("Couldn't serialize maze to JSON")
+    }
+
+    fn walls_to_bitmask(maze: &Maze<R, C>, cell: Cell<R, C>) -> u8 {
+        Self::walls_from_bitmask(maze.get_cell_state(cell).bits()).bits()
+    }
+
+    fn walls_from_bitmask(value: u8) -> CellState {
+        CellState::from_bits_truncate(value)
+            & (CellState::NorthWall
+                | CellState::EastWall
+                | CellState::SouthWall
+                | CellState::WestWall)
+    }
+
+    fn parse_ascii(&self) -> Result<Maze<R, C>, MazefileError> {
+        let mut lines = self.lines().skip_while(|line| line.content.is_empty());
+
+        let mut maze = Maze::<R, C>::new();
+
+        let mut wall_line = Self::next_line(&mut lines, 0)?;
+
         for y in 0..R {
             let row = R - y - 1;
 
+            Self::check_width(&wall_line)?;
             for x in 0..C {
-                let cell = Cell::new(x, row)?;
+                let cell = Cell::new(x, row).expect("Coordinates should be in bounds");
 
-                match lines[line_index].as_bytes()[x * 4 + 2] {
-                    b'-' => maze.update_cell_state(cell, CellState::NorthWall, true),
-                    _ => {}
+                Self::expect_posts(&wall_line, x)?;
+
+                if wall_line.expect(x * 4 + 2, &[HORIZONTAL_WALL, EMPTY])? == HORIZONTAL_WALL {
+                    maze.update_cell_state(cell, CellState::NorthWall, true);
                 }
             }
 
-            line_index += 1;
+            let cell_line = Self::next_line(&mut lines, wall_line.number)?;
+
+            Self::check_width(&cell_line)?;
             for x in 0..C {
-                let cell = Cell::new(x, row)?;
+                let cell = Cell::new(x, row).expect("Coordinates should be in bounds");
 
-                match lines[line_index].as_bytes()[x * 4] {
-                    b'|' => maze.update_cell_state(cell, CellState::WestWall, true),
-                    _ => {}
+                if cell_line.expect(x * 4, &[VERTICAL_WALL, EMPTY])? == VERTICAL_WALL {
+                    maze.update_cell_state(cell, CellState::WestWall, true);
                 }
 
-                match lines[line_index].as_bytes()[x * 4 + 4] {
-                    b'|' => maze.update_cell_state(cell, CellState::EastWall, true),
-                    _ => {}
+                if cell_line.expect(x * 4 + 4, &[VERTICAL_WALL, EMPTY])? == VERTICAL_WALL {
+                    maze.update_cell_state(cell, CellState::EastWall, true);
                 }
 
-                match lines[line_index].as_bytes()[x * 4 + 2] {
-                    b'G' => maze.set_goal_cell(cell)?,
-                    b'S' => maze.set_start_cell(cell),
+                match cell_line.expect(x * 4 + 2, &[GOAL, START, EMPTY])? {
+                    GOAL => maze.set_goal_cell(cell),
+                    START if maze.has_start_cell() => {
+                        return Err(
+                            cell_line.error(x * 4 + 2, "start cell is already marked".to_string())
+                        )
+                    }
+                    START => maze.set_start_cell(cell),
                     _ => {}
                 }
             }
 
-            line_index += 1;
+            wall_line = Self::next_line(&mut lines, cell_line.number)?;
+
+            Self::check_width(&wall_line)?;
             for x in 0..C {
-                let cell = Cell::new(x, row)?;
+                let cell = Cell::new(x, row).expect("Coordinates should be in bounds");
 
-                match lines[line_index].as_bytes()[x * 4 + 2] {
-                    b'-' => maze.update_cell_state(cell, CellState::SouthWall, true),
-                    _ => {}
+                Self::expect_posts(&wall_line, x)?;
+
+                if wall_line.expect(x * 4 + 2, &[HORIZONTAL_WALL, EMPTY])? == HORIZONTAL_WALL {
+                    maze.update_cell_state(cell, CellState::SouthWall, true);
                 }
             }
         }
 
+        if let Some(line) = lines.find(|line| !line.content.is_empty()) {
+            return Err(line.error(
+                0,
+                format!("expected end of file after {} rows of the maze", R),
+            ));
+        }
+
         Ok(maze)
     }
+
+    fn next_line<'a>(
+        lines: &mut impl Iterator<Item = Line<'a>>,
+        previous: usize,
+    ) -> Result<Line<'a>, MazefileError> {
+        lines.next().ok_or_else(|| {
+            MazefileError::new(
+                previous + 1,
+                1,
+                format!(
+                    "unexpected end of file, a {}x{} maze needs {} lines",
+                    C,
+                    R,
+                    R * 2 + 1
+                ),
+            )
+        })
+    }
+
+    fn check_width(line: &Line) -> Result<(), MazefileError> {
+        if line.content.len() > C * 4 + 1 {
+            return Err(line.error(
+                C * 4 + 1,
+                format!("expected end of line, maze is wider than {} columns", C),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn expect_posts(line: &Line, x: usize) -> Result<(), MazefileError> {
+        line.expect(x * 4, &[POST])?;
+        line.expect(x * 4 + 4, &[POST])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[u8], format: MazefileFormat) -> Result<Maze<4, 4>> {
+        Mazefile::<4, 4>::from_bytes(input.to_vec())
+            .with_format(format)
+            .parse()
+    }
+
+    fn parse_error(input: &[u8], format: MazefileFormat) -> String {
+        parse(input, format)
+            .err()
+            .expect("Mazefile should be rejected")
+            .to_string()
+    }
+
+    /// Walled 4x4 maze with a few inner walls, the start in a corner and a 2x2 goal
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for i in 0..4 {
+            maze.update_cell_state(Cell { x: i, y: 0 }, CellState::SouthWall, true);
+            maze.update_cell_state(Cell { x: i, y: 3 }, CellState::NorthWall, true);
+            maze.update_cell_state(Cell { x: 0, y: i }, CellState::WestWall, true);
+            maze.update_cell_state(Cell { x: 3, y: i }, CellState::EastWall, true);
+        }
+
+        maze.update_cell_state(Cell { x: 0, y: 0 }, CellState::EastWall, true);
+        maze.update_cell_state(Cell { x: 1, y: 1 }, CellState::NorthWall, true);
+        maze.update_cell_state(Cell { x: 2, y: 1 }, CellState::WestWall, true);
+
+        maze.set_start_cell(Cell { x: 0, y: 0 });
+        maze.set_goal_cell(Cell { x: 2, y: 2 });
+        maze.set_goal_cell(Cell { x: 3, y: 2 });
+        maze.set_goal_cell(Cell { x: 2, y: 3 });
+        maze.set_goal_cell(Cell { x: 3, y: 3 });
+
+        maze
+    }
+
+    fn round_trip(format: MazefileFormat) -> Maze<4, 4> {
+        let maze = maze();
+
+        let bytes = Mazefile::from_maze(&maze, format)
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+
+        let parsed = parse(&bytes, format).unwrap();
+
+        for x in 0..4 {
+            for y in 0..4 {
+                let cell = Cell { x, y };
+
+                assert_eq!(
+                    parsed.get_cell_state(cell),
+                    maze.get_cell_state(cell),
+                    "walls of ({x}, {y}) in {format:?}"
+                );
+            }
+        }
+
+        parsed
+    }
+
+    fn assert_markers(maze: &Maze<4, 4>) {
+        assert!(maze.has_start_cell());
+        assert_eq!(maze.get_start_cell(), Cell { x: 0, y: 0 });
+        assert_eq!(maze.get_goal_cells(), self::maze().get_goal_cells());
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        assert_markers(&round_trip(MazefileFormat::Ascii));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let maze = round_trip(MazefileFormat::Binary);
+
+        assert!(!maze.has_start_cell());
+        assert!(maze.get_goal_cells().is_empty());
+    }
+
+    #[test]
+    fn numeric_round_trip() {
+        let maze = round_trip(MazefileFormat::Numeric);
+
+        assert!(!maze.has_start_cell());
+        assert!(maze.get_goal_cells().is_empty());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        assert_markers(&round_trip(MazefileFormat::Json));
+    }
+
+    #[test]
+    fn written_formats_are_detected() {
+        for format in [
+            MazefileFormat::Ascii,
+            MazefileFormat::Numeric,
+            MazefileFormat::Json,
+        ] {
+            let mazefile = Mazefile::from_maze(&maze(), format).unwrap();
+
+            assert_eq!(MazefileFormat::detect(mazefile.as_bytes()), format);
+        }
+    }
+
+    #[test]
+    fn json_error_names_the_size() {
+        let input = br#"{"width": 5, "height": 4, "start": null, "goal": [], "walls": []}"#;
+
+        assert_eq!(
+            parse_error(input, MazefileFormat::Json),
+            "JSON mazefile describes a 5x4 maze, expected 4x4"
+        );
+    }
+
+    #[test]
+    fn empty_mazefile_is_rejected() {
+        for format in [
+            MazefileFormat::Ascii,
+            MazefileFormat::Binary,
+            MazefileFormat::Numeric,
+            MazefileFormat::Json,
+        ] {
+            assert_eq!(parse_error(b"", format), "Mazefile is empty");
+        }
+
+        for format in [
+            MazefileFormat::Ascii,
+            MazefileFormat::Numeric,
+            MazefileFormat::Json,
+        ] {
+            assert_eq!(parse_error(b" \n\t\n", format), "Mazefile is empty");
+        }
+    }
+
+    #[test]
+    fn binary_whitespace_bytes_are_walls() {
+        let input = [0x09, 0x0a, 0x0c, 0x0d].repeat(4);
+
+        let maze = parse(&input, MazefileFormat::Binary).unwrap();
+
+        for (index, value) in input.iter().enumerate() {
+            let cell = Cell::new(index / 4, index % 4).unwrap();
+
+            assert_eq!(
+                maze.get_cell_state(cell),
+                Mazefile::<4, 4>::walls_from_bitmask(*value)
+            );
+        }
+    }
+
+    #[test]
+    fn binary_error_names_the_size() {
+        assert_eq!(
+            parse_error(&[0x09, 0x03, 0x05], MazefileFormat::Binary),
+            "Binary mazefile has 3 bytes, expected 16 for a 4x4 maze"
+        );
+    }
+
+    #[test]
+    fn numeric_bitmask_above_a_byte_is_rejected() {
+        assert_eq!(
+            parse_error(b"0 0 9\n1 0 265\n", MazefileFormat::Numeric),
+            "line 2, column 5: wall bitmask 265 is larger than 255"
+        );
+    }
+
+    #[test]
+    fn numeric_flags_set_the_walls() {
+        let maze = parse(b"0 0 1 0 1 1\n1 0 0 0 1 0\n", MazefileFormat::Numeric).unwrap();
+
+        assert_eq!(
+            maze.get_cell_state(Cell { x: 0, y: 0 }),
+            CellState::NorthWall | CellState::SouthWall | CellState::WestWall
+        );
+        assert_eq!(
+            maze.get_cell_state(Cell { x: 1, y: 0 }),
+            CellState::SouthWall
+        );
+    }
+
+    #[test]
+    fn ascii_error_names_line_and_column() {
+        let input = b"o---o---o---o---o\n|               |\no   x   o   o   o\n";
+
+        assert_eq!(
+            parse_error(input, MazefileFormat::Ascii),
+            "line 3, column 5: expected 'o', found 'x'"
+        );
+    }
+
+    #[test]
+    fn formats_are_detected_from_content() {
+        assert_eq!(
+            MazefileFormat::detect(b"o---o---o\n|   |   |\n"),
+            MazefileFormat::Ascii
+        );
+        assert_eq!(
+            MazefileFormat::detect(b"0 0 9\n0 1 3\n"),
+            MazefileFormat::Numeric
+        );
+        assert_eq!(
+            MazefileFormat::detect(&[0x0e, 0x0a, 0x09, 0x08]),
+            MazefileFormat::Binary
+        );
+    }
 }
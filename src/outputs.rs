@@ -0,0 +1,86 @@
+use anyhow::{bail, Result};
+use maze_simulator_protocol::{LedColor, DISPLAY_COLUMNS, DISPLAY_LINES};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LED_COUNT: usize = 4;
+
+/// LEDs, buzzer and display of the runner, set by requests and shown in the panel
+pub struct RunnerOutputs {
+    leds: Vec<LedColor>,
+    // Frequency of the tone and when it stops
+    beep: Option<(u16, Instant)>,
+    display: [[u8; DISPLAY_COLUMNS]; DISPLAY_LINES],
+}
+
+impl RunnerOutputs {
+    pub fn new(led_count: usize) -> Self {
+        Self {
+            leds: vec![LedColor::OFF; led_count],
+            beep: None,
+            display: [[b' '; DISPLAY_COLUMNS]; DISPLAY_LINES],
+        }
+    }
+
+    /// Switches everything off, keeping the number of LEDs
+    pub fn reset(&mut self) {
+        *self = Self::new(self.leds.len());
+    }
+
+    pub fn get_leds(&self) -> &[LedColor] {
+        &self.leds
+    }
+
+    pub fn set_led(&mut self, index: usize, color: LedColor) -> Result<()> {
+        let count = self.leds.len();
+
+        let Some(led) = self.leds.get_mut(index) else {
+            bail!("There is no LED {index}, the runner has {count}");
+        };
+
+        *led = color;
+
+        Ok(())
+    }
+
+    /// A new tone replaces the one playing, a zero frequency or duration silences the buzzer
+    pub fn beep(&mut self, frequency: u16, duration: Duration) {
+        self.beep =
+            (frequency > 0 && !duration.is_zero()).then(|| (frequency, Instant::now() + duration));
+    }
+
+    /// Frequency of the tone playing right now
+    pub fn get_beep(&self) -> Option<u16> {
+        self.beep
+            .filter(|(_, until)| Instant::now() < *until)
+            .map(|(frequency, _)| frequency)
+    }
+
+    pub fn set_display_line(&mut self, line: usize, text: [u8; DISPLAY_COLUMNS]) -> Result<()> {
+        let Some(display_line) = self.display.get_mut(line) else {
+            bail!("There is no display line {line}, the display has {DISPLAY_LINES}");
+        };
+
+        *display_line = text;
+
+        Ok(())
+    }
+
+    pub fn clear_display(&mut self) {
+        self.display = [[b' '; DISPLAY_COLUMNS]; DISPLAY_LINES];
+    }
+
+    /// Display lines with anything but printable ASCII shown as blanks
+    pub fn get_display_lines(&self) -> Vec<String> {
+        self.display
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|&byte| match byte {
+                        0x20..=0x7e => byte as char,
+                        _ => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
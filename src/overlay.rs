@@ -0,0 +1,96 @@
+use anyhow::Result;
+use pix_engine::prelude::*;
+
+use crate::{
+    engine::Render,
+    panel::{format_duration, PANEL_X_OFFSET},
+    shared_maze::SharedMaze,
+    solver::{MazeSolver, PathMetric, Solution},
+};
+
+const METRICS: [(&str, PathMetric); 3] = [
+    ("Cells", PathMetric::Cells),
+    ("Turns", PathMetric::Turns),
+    ("Time", PathMetric::Time),
+];
+
+/// Optimal route drawn over the maze, solved again whenever the maze or the metric changes
+pub struct PathOverlay<const R: usize, const C: usize> {
+    enabled: bool,
+    metric: usize,
+    solved: Option<(u64, usize)>,
+    solution: Option<Solution<R, C>>,
+}
+
+impl<const R: usize, const C: usize> PathOverlay<R, C> {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            metric: 0,
+            solved: None,
+            solution: None,
+        }
+    }
+
+    fn update(&mut self, maze: &SharedMaze<R, C>) {
+        let key = (maze.get_revision(), self.metric);
+
+        if self.solved == Some(key) {
+            return;
+        }
+
+        let maze = maze.read();
+
+        self.solution = MazeSolver::new(&maze).solve(METRICS[self.metric].1);
+        self.solved = Some(key);
+    }
+
+    pub fn draw_path(&mut self, s: &mut PixState, maze: &SharedMaze<R, C>) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        self.update(maze);
+
+        if let Some(solution) = &self.solution {
+            solution.draw(s, Color::DODGER_BLUE, Color::LIGHT_BLUE)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw_controls(&mut self, s: &mut PixState) -> Result<()> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 340;
+
+        s.set_cursor_pos([x_offset, y_offset]);
+
+        s.checkbox("Optimal path", &mut self.enabled)?;
+
+        if !self.enabled {
+            return Ok(());
+        }
+
+        for (i, (label, _)) in METRICS.iter().enumerate() {
+            s.same_line(None);
+            s.radio(label, &mut self.metric, i)?;
+        }
+
+        s.fill(Color::BLACK);
+        s.stroke(None);
+
+        let summary = match &self.solution {
+            Some(solution) => format!(
+                "{} cells, {} turns, {}",
+                solution.get_cell_count(),
+                solution.turns,
+                format_duration(Some(solution.time))
+            ),
+            None => "Goal is not reachable".to_string(),
+        };
+
+        s.text(summary)?;
+
+        Ok(())
+    }
+}
@@ -1,31 +1,46 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anyhow::Result;
-use pix_engine::{prelude::Color, rect, state::PixState};
+use maze_simulator_protocol::ButtonsState;
+use pix_engine::{circle, prelude::Color, rect, state::PixState};
 
 use crate::{
-    communication::ButtonsState,
+    battery::Battery,
     distance_sensors::DistanceSensorsReading,
     engine::Render,
+    outputs::RunnerOutputs,
+    run::RunLog,
     simulator::{APP_HEIGHT, APP_WIDTH, PANEL_WIDTH},
 };
 
 pub const PANEL_Y_OFFSET: i32 = 0;
 pub const PANEL_X_OFFSET: i32 = APP_WIDTH as i32 - PANEL_WIDTH;
 
-pub struct SimPanel {
+pub struct SimPanel<const R: usize, const C: usize> {
     buttons: Arc<Mutex<ButtonsState>>,
+    outputs: Arc<Mutex<RunnerOutputs>>,
+    battery: Arc<Mutex<Battery>>,
     distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
+    run_log: Arc<Mutex<RunLog<R, C>>>,
 }
 
-impl SimPanel {
+impl<const R: usize, const C: usize> SimPanel<R, C> {
     pub fn new(
         buttons: Arc<Mutex<ButtonsState>>,
+        outputs: Arc<Mutex<RunnerOutputs>>,
+        battery: Arc<Mutex<Battery>>,
         distance_sensors: Arc<Mutex<DistanceSensorsReading>>,
+        run_log: Arc<Mutex<RunLog<R, C>>>,
     ) -> Self {
         Self {
             buttons,
+            outputs,
+            battery,
             distance_sensors,
+            run_log,
         }
     }
 
@@ -109,6 +124,61 @@ impl SimPanel {
         Ok(())
     }
 
+    fn draw_run_stats(&self, s: &mut PixState) -> Result<()> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 200;
+        let x_padding = 120;
+        let y_padding = 20;
+
+        let run_log = self.run_log.lock().unwrap();
+
+        let rows = [
+            (
+                "Maze:".to_string(),
+                format!(
+                    "{} / {}",
+                    format_duration(Some(run_log.get_maze_time())),
+                    format_duration(Some(run_log.get_maze_time_limit()))
+                ),
+            ),
+            (
+                "Run:".to_string(),
+                format_duration(run_log.get_current_run_time()),
+            ),
+            (
+                "Search:".to_string(),
+                format_duration(run_log.get_search_run_time()),
+            ),
+            (
+                "Fastest:".to_string(),
+                format_duration(run_log.get_fastest_run_time()),
+            ),
+            (
+                format!("Score {:?}:", run_log.get_rules()),
+                format_duration(run_log.get_score()),
+            ),
+            (
+                "Crashes:".to_string(),
+                format!("{}", run_log.get_crash_count()),
+            ),
+        ];
+
+        s.fill(Color::BLACK);
+        s.stroke(None);
+
+        for (i, (label, value)) in rows.iter().enumerate() {
+            s.set_cursor_pos([x_offset, y_offset + y_padding * i as i32]);
+
+            s.text(label)?;
+
+            s.set_cursor_pos([x_offset + x_padding, y_offset + y_padding * i as i32]);
+
+            s.text(value)?;
+        }
+
+        Ok(())
+    }
+
     fn draw_buttons(&self, s: &mut PixState) -> Result<()> {
         s.set_cursor_pos([PANEL_X_OFFSET + 10, 5]);
         s.fill(Color::BLACK);
@@ -144,9 +214,123 @@ impl SimPanel {
 
         Ok(())
     }
+
+    fn draw_outputs(&self, s: &mut PixState) -> Result<()> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 585;
+        let y_padding = 20;
+        let led_padding = 25;
+        let led_radius = 8;
+        let line_height = 18;
+
+        let outputs = self.outputs.lock().unwrap();
+
+        s.fill(Color::BLACK);
+        s.stroke(None);
+
+        s.set_cursor_pos([x_offset, y_offset]);
+
+        s.text("LEDs:")?;
+
+        s.set_cursor_pos([x_offset, y_offset + y_padding]);
+
+        match outputs.get_beep() {
+            Some(frequency) => s.text(format!("Buzzer: {} Hz", frequency))?,
+            None => s.text("Buzzer: off")?,
+        };
+
+        s.stroke(Color::BLACK);
+
+        for (i, led) in outputs.get_leds().iter().enumerate() {
+            s.fill(Color::rgb(led.red, led.green, led.blue));
+
+            s.circle(circle![
+                x_offset + 60 + led_padding * i as i32,
+                y_offset + led_radius,
+                led_radius,
+            ])?;
+        }
+
+        let lines = outputs.get_display_lines();
+
+        s.fill(Color::BLACK);
+
+        s.rect(rect![
+            x_offset,
+            y_offset + y_padding * 2 + 5,
+            220,
+            line_height * lines.len() as i32 + 10,
+        ])?;
+
+        s.fill(Color::LIGHT_GREEN);
+        s.stroke(None);
+
+        for (i, line) in lines.iter().enumerate() {
+            s.set_cursor_pos([
+                x_offset + 5,
+                y_offset + y_padding * 2 + 10 + line_height * i as i32,
+            ]);
+
+            s.text(line)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_battery(&self, s: &mut PixState) -> Result<()> {
+        let x_offset = PANEL_X_OFFSET + 240;
+        let y_offset = 630;
+        let y_padding = 20;
+
+        let (voltage, current, cut_off) = {
+            let battery = self.battery.lock().unwrap();
+
+            (
+                battery.get_voltage(),
+                battery.get_current(),
+                battery.is_cut_off(),
+            )
+        };
+
+        s.fill(if cut_off {
+            Color::DARK_RED
+        } else {
+            Color::BLACK
+        });
+        s.stroke(None);
+
+        let lines = [
+            "Battery:".to_string(),
+            format!("{:.2} V", voltage),
+            format!("{:.2} A", current),
+        ];
+
+        for (i, line) in lines.iter().enumerate() {
+            s.set_cursor_pos([x_offset, y_offset + y_padding * i as i32]);
+
+            s.text(line)?;
+        }
+
+        if s.button("Charge")? {
+            self.battery.lock().unwrap().charge();
+        }
+
+        Ok(())
+    }
+}
+
+pub fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!(
+            "{}:{:06.3}",
+            duration.as_secs() / 60,
+            duration.as_secs_f64() % 60.0
+        ),
+        None => "-".to_string(),
+    }
 }
 
-impl Render for SimPanel {
+impl<const R: usize, const C: usize> Render for SimPanel<R, C> {
     fn draw<T>(&self, s: &mut PixState, primary_color: T, secondary_color: T) -> Result<()>
     where
         T: Into<Option<Color>>,
@@ -165,6 +349,12 @@ impl Render for SimPanel {
 
         self.draw_sensor_readings(s)?;
 
+        self.draw_run_stats(s)?;
+
+        self.draw_outputs(s)?;
+
+        self.draw_battery(s)?;
+
         Ok(())
     }
 }
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use maze_simulator_protocol::MazeRunnerRequest;
+use pix_engine::prelude::*;
+use std::sync::mpsc::Sender;
+
+use crate::{
+    communication::SimEvent,
+    panel::PANEL_X_OFFSET,
+    runner::MazeOrientation,
+    simulator::{CELL_SIZE_MM, CELL_SIZE_VIS, RATIO_VIS_MM, WALL_WIDTH_MM, WALL_WIDTH_VIS},
+};
+
+// Clockwise from north like the runner orientations
+const HEADING_LABELS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// Puts the runner down where the maze is clicked, to try a maneuver without driving there first
+pub struct RunnerPlacement<const R: usize, const C: usize> {
+    enabled: bool,
+    heading: usize,
+    events: Sender<SimEvent>,
+}
+
+impl<const R: usize, const C: usize> RunnerPlacement<R, C> {
+    pub fn new(events: Sender<SimEvent>) -> Self {
+        Self {
+            enabled: false,
+            heading: 0,
+            events,
+        }
+    }
+
+    /// Left click places the runner on the nearest cell center or wall midpoint
+    pub fn click(&mut self, button: Mouse, position: Point<i32>) -> bool {
+        if !self.enabled || button != Mouse::Left {
+            return false;
+        }
+
+        let (px, py) = (position.x(), position.y());
+
+        let maze_width = CELL_SIZE_VIS * C as i32 + WALL_WIDTH_VIS;
+        let maze_height = CELL_SIZE_VIS * R as i32 + WALL_WIDTH_VIS;
+
+        if px < 0 || py < 0 || px >= maze_width || py >= maze_height {
+            return false;
+        }
+
+        let x = (px * RATIO_VIS_MM - WALL_WIDTH_MM / 2) as f64;
+        let y = (R as i32 * CELL_SIZE_MM + WALL_WIDTH_MM / 2 - py * RATIO_VIS_MM) as f64;
+
+        let half_cell = CELL_SIZE_MM as f64 / 2.0;
+
+        let request = MazeRunnerRequest::SetPose {
+            x: (x / half_cell).round() * half_cell,
+            y: (y / half_cell).round() * half_cell,
+            theta: MazeOrientation::from_eighths(self.heading)
+                .get_angle()
+                .as_degrees(),
+        };
+
+        if self.events.send(SimEvent::Panel(request)).is_err() {
+            println!("Runner can't be placed, the environment has stopped");
+        }
+
+        true
+    }
+
+    pub fn draw_controls(&mut self, s: &mut PixState) -> Result<()> {
+        let x_offset = PANEL_X_OFFSET + 10;
+        let y_offset = 55;
+
+        s.set_cursor_pos([x_offset, y_offset]);
+
+        s.checkbox("Place runner", &mut self.enabled)?;
+
+        if !self.enabled {
+            return Ok(());
+        }
+
+        for (i, label) in HEADING_LABELS.iter().enumerate() {
+            if i > 0 {
+                s.same_line(None);
+            }
+
+            s.radio(label, &mut self.heading, i)?;
+        }
+
+        Ok(())
+    }
+}
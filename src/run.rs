@@ -0,0 +1,336 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    maze::{Cell, Maze},
+    runner::MazeOrientation,
+    scoring::ScoringRules,
+};
+
+#[derive(Copy, Clone)]
+pub enum RunEvent<const R: usize, const C: usize> {
+    Crash {
+        cell: Cell<R, C>,
+        orientation: MazeOrientation,
+    },
+    RunStarted {
+        maze_time: Duration,
+    },
+    GoalReached {
+        maze_time: Duration,
+        run_time: Duration,
+    },
+    RunAborted {
+        maze_time: Duration,
+    },
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RunRecord {
+    /// Maze time elapsed when the run left the start cell
+    pub maze_time: Duration,
+    /// Time from leaving the start cell to reaching the goal
+    pub duration: Duration,
+    /// Crashes recorded since the maze time started and before this run
+    pub crashes_before: usize,
+}
+
+pub struct RunLog<const R: usize, const C: usize> {
+    events: Vec<RunEvent<R, C>>,
+    runs: Vec<RunRecord>,
+    rules: ScoringRules,
+    maze_time_limit: Duration,
+    maze_start: Option<Instant>,
+    run_start: Option<Instant>,
+}
+
+impl<const R: usize, const C: usize> Default for RunLog<R, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const R: usize, const C: usize> RunLog<R, C> {
+    pub fn new() -> Self {
+        let rules = ScoringRules::default();
+
+        Self {
+            events: Vec::new(),
+            runs: Vec::new(),
+            rules,
+            maze_time_limit: rules.default_maze_time(),
+            maze_start: None,
+            run_start: None,
+        }
+    }
+
+    pub fn set_rules(&mut self, rules: ScoringRules) {
+        self.rules = rules;
+    }
+
+    pub fn set_maze_time_limit(&mut self, maze_time_limit: Duration) {
+        self.maze_time_limit = maze_time_limit;
+    }
+
+    /// Clears the recorded runs for a new maze, keeping the rules and the maze time limit
+    pub fn reset(&mut self) {
+        self.events.clear();
+        self.runs.clear();
+        self.maze_start = None;
+        self.run_start = None;
+    }
+
+    pub fn record(&mut self, event: RunEvent<R, C>) {
+        self.events.push(event);
+    }
+
+    pub fn runner_moved(&mut self, maze: &Maze<R, C>, from: Cell<R, C>, to: Cell<R, C>) {
+        self.runner_moved_at(maze, from, to, Instant::now());
+    }
+
+    fn runner_moved_at(
+        &mut self,
+        maze: &Maze<R, C>,
+        from: Cell<R, C>,
+        to: Cell<R, C>,
+        now: Instant,
+    ) {
+        let start = maze.get_start_cell();
+
+        if from == start && to != start {
+            self.start_run(now);
+        } else if to == start {
+            self.abort_run_at(now);
+        }
+
+        if maze.is_goal_cell(to) {
+            self.finish_run(now);
+        }
+    }
+
+    pub fn abort_run(&mut self) {
+        self.abort_run_at(Instant::now());
+    }
+
+    fn abort_run_at(&mut self, now: Instant) {
+        if self.run_start.take().is_some() {
+            let maze_time = self.get_maze_time_at(now);
+
+            self.record(RunEvent::RunAborted { maze_time });
+        }
+    }
+
+    fn start_run(&mut self, now: Instant) {
+        if self.get_maze_time_at(now) > self.maze_time_limit {
+            return;
+        }
+
+        self.maze_start.get_or_insert(now);
+        self.run_start = Some(now);
+
+        let maze_time = self.get_maze_time_at(now);
+
+        self.record(RunEvent::RunStarted { maze_time });
+    }
+
+    fn finish_run(&mut self, now: Instant) {
+        let Some(run_start) = self.run_start.take() else {
+            return;
+        };
+
+        let maze_start = self.maze_start.unwrap_or(run_start);
+
+        // Both from the same instant, so the run never outlasts the maze time
+        let maze_time = now.duration_since(maze_start);
+        let run_time = now.duration_since(run_start);
+
+        self.record(RunEvent::GoalReached {
+            maze_time,
+            run_time,
+        });
+
+        if maze_time > self.maze_time_limit {
+            return;
+        }
+
+        self.runs.push(RunRecord {
+            maze_time: run_start.duration_since(maze_start),
+            duration: run_time,
+            crashes_before: self.get_crash_count(),
+        });
+    }
+
+    pub fn get_events(&self) -> &[RunEvent<R, C>] {
+        &self.events
+    }
+
+    pub fn get_runs(&self) -> &[RunRecord] {
+        &self.runs
+    }
+
+    pub fn get_rules(&self) -> ScoringRules {
+        self.rules
+    }
+
+    pub fn get_crash_count(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, RunEvent::Crash { .. }))
+            .count()
+    }
+
+    pub fn get_maze_time(&self) -> Duration {
+        self.get_maze_time_at(Instant::now())
+    }
+
+    fn get_maze_time_at(&self, now: Instant) -> Duration {
+        self.maze_start
+            .map(|maze_start| now.saturating_duration_since(maze_start))
+            .unwrap_or_default()
+    }
+
+    pub fn get_maze_time_limit(&self) -> Duration {
+        self.maze_time_limit
+    }
+
+    pub fn is_maze_time_over(&self) -> bool {
+        self.get_maze_time() > self.maze_time_limit
+    }
+
+    pub fn get_current_run_time(&self) -> Option<Duration> {
+        self.run_start.map(|run_start| run_start.elapsed())
+    }
+
+    pub fn get_search_run_time(&self) -> Option<Duration> {
+        self.runs.first().map(|run| run.duration)
+    }
+
+    pub fn get_fastest_run_time(&self) -> Option<Duration> {
+        self.runs.iter().map(|run| run.duration).min()
+    }
+
+    pub fn get_score(&self) -> Option<Duration> {
+        self.rules.score(&self.runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::new();
+
+        maze.set_start_cell(Cell { x: 0, y: 0 });
+        maze.set_goal_cell(Cell { x: 3, y: 3 });
+
+        maze
+    }
+
+    const START: Cell<4, 4> = Cell { x: 0, y: 0 };
+    const NEXT: Cell<4, 4> = Cell { x: 0, y: 1 };
+    const BEFORE_GOAL: Cell<4, 4> = Cell { x: 3, y: 2 };
+    const GOAL: Cell<4, 4> = Cell { x: 3, y: 3 };
+
+    #[test]
+    fn first_run_starts_at_zero_maze_time() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0);
+
+        let runs = run_log.get_runs();
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].maze_time, Duration::ZERO);
+        assert_eq!(runs[0].duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn later_runs_record_the_maze_time_they_started_at() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 5 * SECOND);
+        run_log.runner_moved_at(&maze, NEXT, START, t0 + 15 * SECOND);
+        run_log.runner_moved_at(&maze, START, NEXT, t0 + 20 * SECOND);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 24 * SECOND);
+
+        let runs = run_log.get_runs();
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[1].maze_time, 20 * SECOND);
+        assert_eq!(runs[1].duration, 4 * SECOND);
+        assert_eq!(run_log.get_search_run_time(), Some(5 * SECOND));
+        assert_eq!(run_log.get_fastest_run_time(), Some(4 * SECOND));
+    }
+
+    #[test]
+    fn returning_to_start_aborts_the_run() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.runner_moved_at(&maze, NEXT, START, t0 + 3 * SECOND);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 6 * SECOND);
+
+        assert!(run_log.get_runs().is_empty());
+        assert!(matches!(
+            run_log.get_events().last(),
+            Some(RunEvent::RunAborted { maze_time }) if *maze_time == 3 * SECOND
+        ));
+    }
+
+    #[test]
+    fn runs_finished_after_the_maze_time_are_not_scored() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.set_maze_time_limit(10 * SECOND);
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 11 * SECOND);
+
+        assert!(run_log.get_runs().is_empty());
+        assert_eq!(run_log.get_score(), None);
+    }
+
+    #[test]
+    fn runs_count_the_crashes_before_them() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.record(RunEvent::Crash {
+            cell: NEXT,
+            orientation: MazeOrientation::North,
+        });
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 5 * SECOND);
+
+        assert_eq!(run_log.get_crash_count(), 1);
+        assert_eq!(run_log.get_runs()[0].crashes_before, 1);
+    }
+
+    #[test]
+    fn reset_clears_runs_and_keeps_the_rules() {
+        let maze = maze();
+        let mut run_log = RunLog::new();
+        let t0 = Instant::now();
+
+        run_log.set_rules(ScoringRules::Japan);
+        run_log.runner_moved_at(&maze, START, NEXT, t0);
+        run_log.runner_moved_at(&maze, BEFORE_GOAL, GOAL, t0 + 5 * SECOND);
+        run_log.reset();
+
+        assert!(run_log.get_runs().is_empty());
+        assert!(run_log.get_events().is_empty());
+        assert_eq!(run_log.get_rules(), ScoringRules::Japan);
+    }
+}
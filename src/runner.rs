@@ -1,16 +1,99 @@
 use anyhow::{bail, Result};
+use maze_simulator_protocol::{Heading, TurnDirection, TurnKind};
+use std::f64::consts::{PI, SQRT_2};
 
 use crate::{
     maze::{Cell, CellState, Maze},
-    position::{Angle, Position},
+    position::{Angle, Millimeters, Position},
     simulator::CELL_SIZE_MM,
+    trajectory::Trajectory,
 };
 
+const HALF_CELL_MM: Millimeters = CELL_SIZE_MM as f64 / 2.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MazeOrientation {
     North,
+    NorthEast,
     East,
+    SouthEast,
     South,
+    SouthWest,
     West,
+    NorthWest,
+}
+
+// Clockwise, so turning right steps forward through the list
+const ORIENTATIONS: [MazeOrientation; 8] = [
+    MazeOrientation::North,
+    MazeOrientation::NorthEast,
+    MazeOrientation::East,
+    MazeOrientation::SouthEast,
+    MazeOrientation::South,
+    MazeOrientation::SouthWest,
+    MazeOrientation::West,
+    MazeOrientation::NorthWest,
+];
+
+impl MazeOrientation {
+    pub fn is_diagonal(&self) -> bool {
+        (*self as usize) % 2 != 0
+    }
+
+    /// Heading clockwise from north in 45 degree steps
+    pub fn from_eighths(eighths: usize) -> Self {
+        ORIENTATIONS[eighths % 8]
+    }
+
+    /// North, east, south and west
+    pub fn cardinals() -> impl Iterator<Item = Self> {
+        ORIENTATIONS.into_iter().step_by(2)
+    }
+
+    /// Turned by multiples of 45 degrees
+    pub fn turned(&self, direction: RotationDirection, eighths: usize) -> Self {
+        let index = match direction {
+            RotationDirection::Left => *self as usize + 8 - eighths % 8,
+            RotationDirection::Right => *self as usize + eighths,
+        };
+
+        ORIENTATIONS[index % 8]
+    }
+
+    /// Step to the next cell center or wall midpoint ahead, in half cells
+    pub fn get_step(&self) -> (isize, isize) {
+        match self {
+            MazeOrientation::North => (0, 1),
+            MazeOrientation::NorthEast => (1, 1),
+            MazeOrientation::East => (1, 0),
+            MazeOrientation::SouthEast => (1, -1),
+            MazeOrientation::South => (0, -1),
+            MazeOrientation::SouthWest => (-1, -1),
+            MazeOrientation::West => (-1, 0),
+            MazeOrientation::NorthWest => (-1, 1),
+        }
+    }
+
+    /// Wall crossed when leaving a cell this way, none on a diagonal
+    pub fn get_wall(&self) -> Option<CellState> {
+        match self {
+            MazeOrientation::North => Some(CellState::NorthWall),
+            MazeOrientation::East => Some(CellState::EastWall),
+            MazeOrientation::South => Some(CellState::SouthWall),
+            MazeOrientation::West => Some(CellState::WestWall),
+            _ => None,
+        }
+    }
+
+    pub fn get_angle(&self) -> Angle {
+        Angle::degrees(90.0 - 45.0 * *self as usize as f64)
+    }
+}
+
+impl From<Heading> for MazeOrientation {
+    fn from(heading: Heading) -> Self {
+        Self::from_eighths(heading as usize)
+    }
 }
 
 pub enum SensorDirection {
@@ -25,8 +108,22 @@ pub enum RotationDirection {
     Right,
 }
 
+impl From<TurnDirection> for RotationDirection {
+    fn from(direction: TurnDirection) -> Self {
+        match direction {
+            TurnDirection::Left => RotationDirection::Left,
+            TurnDirection::Right => RotationDirection::Right,
+        }
+    }
+}
+
+type HalfCells = (isize, isize);
+
+#[derive(Clone)]
 pub struct MazerRunner<const R: usize, const C: usize> {
-    cell: Cell<R, C>,
+    // Cell centers have odd coordinates, wall midpoints one odd and one even
+    x: usize,
+    y: usize,
     orientation: MazeOrientation,
 }
 
@@ -48,80 +145,350 @@ impl<const R: usize, const C: usize> MazerRunner<R, C> {
         };
 
         Ok(Self {
-            cell: start_cell,
+            x: 2 * start_cell.x + 1,
+            y: 2 * start_cell.y + 1,
             orientation,
         })
     }
 
+    pub fn in_cell(cell: Cell<R, C>, orientation: MazeOrientation) -> Result<Self> {
+        Self::placed(2 * cell.x + 1, 2 * cell.y + 1, orientation)
+    }
+
+    /// Runner at the cell center or wall midpoint nearest to the pose, facing the nearest of the
+    /// 8 headings
+    pub fn nearest(position: &Position<R>) -> Result<Self> {
+        let x = (position.x / HALF_CELL_MM).round();
+        let y = (position.y / HALF_CELL_MM).round();
+
+        if x <= 0.0 || y <= 0.0 || x >= 2.0 * C as f64 || y >= 2.0 * R as f64 {
+            bail!(
+                "Pose ({:.0}, {:.0}) mm is outside the maze",
+                position.x,
+                position.y
+            );
+        }
+
+        let (x, y) = (x as usize, y as usize);
+
+        if x % 2 == 0 && y % 2 == 0 {
+            bail!(
+                "Pose ({:.0}, {:.0}) mm is on a post",
+                position.x,
+                position.y
+            );
+        }
+
+        let eighths = ((90.0 - position.theta.as_degrees()) / 45.0).round();
+
+        Self::placed(x, y, ORIENTATIONS[eighths.rem_euclid(8.0) as usize])
+    }
+
+    /// Cell centers take straight headings, wall midpoints diagonals or straight across the wall
+    fn placed(x: usize, y: usize, orientation: MazeOrientation) -> Result<Self> {
+        let runner = Self { x, y, orientation };
+
+        let (dx, dy) = orientation.get_step();
+
+        let fits = match (runner.is_at_edge(), orientation.is_diagonal()) {
+            (false, diagonal) => !diagonal,
+            (true, true) => true,
+            // Crossing a wall line changes the even coordinate
+            (true, false) => (x % 2 == 0 && dx != 0) || (y % 2 == 0 && dy != 0),
+        };
+
+        if !fits {
+            bail!(
+                "Facing {:?} doesn't fit {}",
+                orientation,
+                if runner.is_at_edge() {
+                    "a wall midpoint, only diagonals or across the wall do"
+                } else {
+                    "a cell center, only straight headings do"
+                }
+            );
+        }
+
+        Ok(runner)
+    }
+
+    /// The cell the runner is in, or the one it is leaving when on a wall midpoint
+    pub fn get_cell(&self) -> Cell<R, C> {
+        let (dx, dy) = self.orientation.get_step();
+
+        let x = (2 * self.x).saturating_add_signed(-dx) / 4;
+        let y = (2 * self.y).saturating_add_signed(-dy) / 4;
+
+        Cell {
+            x: x.min(C - 1),
+            y: y.min(R - 1),
+        }
+    }
+
+    pub fn get_orientation(&self) -> MazeOrientation {
+        self.orientation
+    }
+
+    /// Whether the runner stands on a wall midpoint rather than a cell center
+    pub fn is_at_edge(&self) -> bool {
+        self.x % 2 == 0 || self.y % 2 == 0
+    }
+
     pub fn get_real_position(&self) -> Position<R> {
-        let x = self.cell.x as f64 * CELL_SIZE_MM as f64 + CELL_SIZE_MM as f64 / 2.0;
-        let y = self.cell.y as f64 * CELL_SIZE_MM as f64 + CELL_SIZE_MM as f64 / 2.0;
-
-        let theta = match self.orientation {
-            MazeOrientation::North => Angle::degrees(90.0),
-            MazeOrientation::East => Angle::degrees(0.0),
-            MazeOrientation::South => Angle::degrees(-90.0),
-            MazeOrientation::West => Angle::degrees(180.0),
+        Position::new(
+            self.x as f64 * HALF_CELL_MM,
+            self.y as f64 * HALF_CELL_MM,
+            self.orientation.get_angle(),
+        )
+    }
+
+    fn get_point(&self) -> HalfCells {
+        (self.x as isize, self.y as isize)
+    }
+
+    /// Whether the wall with its midpoint at `point` is there, outside the maze counts as a wall
+    fn has_wall(maze: &Maze<R, C>, (x, y): HalfCells) -> bool {
+        if x < 0 || y < 0 || x > 2 * C as isize || y > 2 * R as isize {
+            return true;
+        }
+
+        let (x, y) = (x as usize, y as usize);
+
+        let (cell, wall) = match (x % 2, y % 2) {
+            (0, 1) if x == 0 => (Cell { x: 0, y: y / 2 }, CellState::WestWall),
+            (0, 1) => (
+                Cell {
+                    x: x / 2 - 1,
+                    y: y / 2,
+                },
+                CellState::EastWall,
+            ),
+            (1, 0) if y == 0 => (Cell { x: x / 2, y: 0 }, CellState::SouthWall),
+            (1, 0) => (
+                Cell {
+                    x: x / 2,
+                    y: y / 2 - 1,
+                },
+                CellState::NorthWall,
+            ),
+            _ => return false,
         };
 
-        Position::new(x, y, theta)
+        maze.get_cell_state(cell).contains(wall)
     }
 
     pub fn is_wall_detected(&self, maze: &Maze<R, C>, direction: SensorDirection) -> bool {
-        let cell_state = maze.get_cell_state(self.cell);
+        let (x, y) = self.get_point();
+        let (dx, dy) = self.orientation.get_step();
 
-        match direction {
-            SensorDirection::Front => match self.orientation {
-                MazeOrientation::East => cell_state.contains(CellState::EastWall),
-                MazeOrientation::North => cell_state.contains(CellState::NorthWall),
-                MazeOrientation::West => cell_state.contains(CellState::WestWall),
-                MazeOrientation::South => cell_state.contains(CellState::SouthWall),
-            },
-
-            SensorDirection::Left => match self.orientation {
-                MazeOrientation::East => cell_state.contains(CellState::NorthWall),
-                MazeOrientation::North => cell_state.contains(CellState::WestWall),
-                MazeOrientation::West => cell_state.contains(CellState::SouthWall),
-                MazeOrientation::South => cell_state.contains(CellState::EastWall),
-            },
-            SensorDirection::Right => match self.orientation {
-                MazeOrientation::East => cell_state.contains(CellState::SouthWall),
-                MazeOrientation::North => cell_state.contains(CellState::EastWall),
-                MazeOrientation::West => cell_state.contains(CellState::NorthWall),
-                MazeOrientation::South => cell_state.contains(CellState::WestWall),
-            },
+        // On a diagonal the runner looks at the walls around the next midpoint, on an edge at
+        // the cell it is entering
+        let (x, y) = if self.orientation.is_diagonal() || self.is_at_edge() {
+            (x + dx, y + dy)
+        } else {
+            (x, y)
+        };
+
+        let (dx, dy) = match direction {
+            SensorDirection::Front if self.orientation.is_diagonal() => (0, 0),
+            SensorDirection::Front => (dx, dy),
+            SensorDirection::Left => (-dy, dx),
+            SensorDirection::Right => (dy, -dx),
+        };
+
+        Self::has_wall(maze, (x + dx, y + dy))
+    }
+
+    pub fn can_move_half(&self, maze: &Maze<R, C>) -> bool {
+        if self.orientation.is_diagonal() {
+            // Diagonal steps pass close to the posts, so they are checked against the real walls
+            return Trajectory::new(self.get_real_position())
+                .straight(HALF_CELL_MM * SQRT_2)
+                .check_clearance(maze)
+                .is_ok();
         }
+
+        self.is_at_edge() || !self.is_wall_detected(maze, SensorDirection::Front)
     }
 
-    pub fn move_forward(&mut self, maze: &Maze<R, C>) -> Result<()> {
-        if self.is_wall_detected(maze, SensorDirection::Front) {
+    /// Moves from the cell center to the edge ahead or from the edge to the next cell center,
+    /// on a diagonal from one wall midpoint to the next
+    pub fn move_half(&mut self, maze: &Maze<R, C>) -> Result<()> {
+        if !self.can_move_half(maze) {
             bail!("Wall in front of Runner");
         }
 
-        match self.orientation {
-            MazeOrientation::North => self.cell.y += 1,
-            MazeOrientation::East => self.cell.x += 1,
-            MazeOrientation::South => self.cell.y -= 1,
-            MazeOrientation::West => self.cell.x -= 1,
-        }
+        let (dx, dy) = self.orientation.get_step();
+
+        self.x = self.x.saturating_add_signed(dx);
+        self.y = self.y.saturating_add_signed(dy);
 
         Ok(())
     }
 
     pub fn rotate(&mut self, direction: RotationDirection) {
-        self.orientation = match direction {
-            RotationDirection::Left => match self.orientation {
-                MazeOrientation::East => MazeOrientation::North,
-                MazeOrientation::North => MazeOrientation::West,
-                MazeOrientation::West => MazeOrientation::South,
-                MazeOrientation::South => MazeOrientation::East,
-            },
-            RotationDirection::Right => match self.orientation {
-                MazeOrientation::East => MazeOrientation::South,
-                MazeOrientation::North => MazeOrientation::East,
-                MazeOrientation::West => MazeOrientation::North,
-                MazeOrientation::South => MazeOrientation::West,
-            },
+        self.orientation = self.orientation.turned(direction, 2);
+    }
+
+    /// Runner state after the turn and the path there, walls are left to the caller
+    pub fn plan_turn(
+        &self,
+        kind: TurnKind,
+        direction: RotationDirection,
+    ) -> Result<(Self, Trajectory<R>)> {
+        let (x, y) = self.get_point();
+        let (dx, dy) = self.orientation.get_step();
+
+        let (sx, sy) = match direction {
+            RotationDirection::Left => (-dy, dx),
+            RotationDirection::Right => (dy, -dx),
+        };
+
+        let diagonal = self.orientation.is_diagonal();
+        let at_edge = self.is_at_edge();
+
+        let start = Trajectory::new(self.get_real_position());
+
+        let half_turn = match direction {
+            RotationDirection::Left => PI,
+            RotationDirection::Right => -PI,
+        };
+
+        let (trajectory, (x, y), orientation) = match kind {
+            TurnKind::Search90 if at_edge && !diagonal => {
+                let waypoints = [(x + dx, y + dy), (x + dx + sx, y + dy + sy)];
+
+                (
+                    start.through(&to_millimeters(&waypoints)),
+                    waypoints[1],
+                    self.orientation.turned(direction, 2),
+                )
+            }
+            TurnKind::Turnaround180 if !diagonal => {
+                let trajectory = match at_edge {
+                    true => start
+                        .straight(HALF_CELL_MM)
+                        .spin(half_turn)
+                        .straight(HALF_CELL_MM),
+                    false => start.spin(half_turn),
+                };
+
+                (trajectory, (x, y), self.orientation.turned(direction, 4))
+            }
+            TurnKind::Large90 if !at_edge && !diagonal => {
+                let waypoints = [
+                    (x + 2 * dx, y + 2 * dy),
+                    (x + 2 * dx + 2 * sx, y + 2 * dy + 2 * sy),
+                ];
+
+                (
+                    start.through(&to_millimeters(&waypoints)),
+                    waypoints[1],
+                    self.orientation.turned(direction, 2),
+                )
+            }
+            TurnKind::Large180 if at_edge && !diagonal => {
+                let waypoints = [
+                    (x + dx, y + dy),
+                    (x + dx + 2 * sx, y + dy + 2 * sy),
+                    (x + 2 * sx, y + 2 * sy),
+                ];
+
+                (
+                    start.through(&to_millimeters(&waypoints)),
+                    waypoints[2],
+                    self.orientation.turned(direction, 4),
+                )
+            }
+            TurnKind::Diagonal90 if diagonal => {
+                let (ex, ey) = self.orientation.turned(direction, 2).get_step();
+                let waypoints = [(x + dx, y + dy), (x + dx + ex, y + dy + ey)];
+
+                (
+                    start.through(&to_millimeters(&waypoints)),
+                    waypoints[1],
+                    self.orientation.turned(direction, 2),
+                )
+            }
+            TurnKind::Fast45 | TurnKind::Fast135 if diagonal || !at_edge => {
+                let eighths = if kind == TurnKind::Fast45 { 1 } else { 3 };
+                let orientation = self.orientation.turned(direction, eighths);
+
+                let (ex, ey) = orientation.get_step();
+                let waypoints = [(x + dx, y + dy), (x + dx + ex, y + dy + ey)];
+
+                // Straights out of a diagonal have to run through cell centers
+                let (end_x, end_y) = waypoints[1];
+
+                if diagonal && (end_x % 2 == 0 || end_y % 2 == 0) {
+                    bail!("{kind:?} turn would end on a wall line, one more diagonal step first");
+                }
+
+                (
+                    start.through(&to_millimeters(&waypoints)),
+                    waypoints[1],
+                    orientation,
+                )
+            }
+            _ => bail!(
+                "{kind:?} turn is not possible facing {:?} {}",
+                self.orientation,
+                if at_edge {
+                    "on a wall midpoint"
+                } else {
+                    "in a cell center"
+                }
+            ),
+        };
+
+        if x <= 0 || y <= 0 || x >= 2 * C as isize || y >= 2 * R as isize {
+            bail!("{kind:?} turn leaves the maze");
+        }
+
+        let next = Self {
+            x: x as usize,
+            y: y as usize,
+            orientation,
         };
+
+        Ok((next, trajectory))
+    }
+}
+
+fn to_millimeters(points: &[HalfCells]) -> Vec<(Millimeters, Millimeters)> {
+    points
+        .iter()
+        .map(|&(x, y)| (x as f64 * HALF_CELL_MM, y as f64 * HALF_CELL_MM))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pose(x: Millimeters, y: Millimeters, theta: f64) -> Position<4> {
+        Position::new(x, y, Angle::degrees(theta))
+    }
+
+    #[test]
+    fn wall_midpoints_take_headings_across_the_wall_or_diagonals() {
+        assert!(MazerRunner::<4, 4>::nearest(&pose(180.0, 90.0, 0.0)).is_ok());
+        assert!(MazerRunner::<4, 4>::nearest(&pose(180.0, 90.0, 45.0)).is_ok());
+        assert!(MazerRunner::<4, 4>::nearest(&pose(180.0, 90.0, 90.0)).is_err());
+        assert!(MazerRunner::<4, 4>::nearest(&pose(90.0, 180.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn cell_centers_take_straight_headings() {
+        let cell = Cell { x: 1, y: 1 };
+
+        assert!(MazerRunner::<4, 4>::in_cell(cell, MazeOrientation::West).is_ok());
+        assert!(MazerRunner::<4, 4>::in_cell(cell, MazeOrientation::NorthEast).is_err());
+    }
+
+    #[test]
+    fn posts_and_outside_are_rejected() {
+        assert!(MazerRunner::<4, 4>::nearest(&pose(180.0, 180.0, 0.0)).is_err());
+        assert!(MazerRunner::<4, 4>::nearest(&pose(-90.0, 90.0, 0.0)).is_err());
     }
 }
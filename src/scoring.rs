@@ -0,0 +1,83 @@
+use clap::ValueEnum;
+use std::time::Duration;
+
+use crate::run::RunRecord;
+
+const SEARCH_TIME_DIVISOR: u32 = 30;
+const CRASH_PENALTY: Duration = Duration::from_secs(3);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ValueEnum)]
+pub enum ScoringRules {
+    /// Fastest run time plus 1/30 of the maze time used before that run started
+    #[default]
+    Uk,
+    /// Fastest run time only
+    Japan,
+    /// Like UK, with an extra 3 second penalty for every crash before the run
+    Apec,
+}
+
+impl ScoringRules {
+    pub fn default_maze_time(&self) -> Duration {
+        match self {
+            ScoringRules::Uk => Duration::from_secs(10 * 60),
+            ScoringRules::Japan => Duration::from_secs(7 * 60),
+            ScoringRules::Apec => Duration::from_secs(10 * 60),
+        }
+    }
+
+    pub fn run_score(&self, run: &RunRecord) -> Duration {
+        match self {
+            ScoringRules::Uk => run.duration + run.maze_time / SEARCH_TIME_DIVISOR,
+            ScoringRules::Japan => run.duration,
+            ScoringRules::Apec => {
+                run.duration
+                    + run.maze_time / SEARCH_TIME_DIVISOR
+                    + CRASH_PENALTY * run.crashes_before as u32
+            }
+        }
+    }
+
+    pub fn score(&self, runs: &[RunRecord]) -> Option<Duration> {
+        runs.iter().map(|run| self.run_score(run)).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn run(maze_time: u32, duration: u32, crashes_before: usize) -> RunRecord {
+        RunRecord {
+            maze_time: maze_time * SECOND,
+            duration: duration * SECOND,
+            crashes_before,
+        }
+    }
+
+    #[test]
+    fn uk_adds_a_thirtieth_of_the_maze_time() {
+        assert_eq!(ScoringRules::Uk.run_score(&run(60, 10, 2)), 12 * SECOND);
+    }
+
+    #[test]
+    fn japan_scores_the_run_time_only() {
+        assert_eq!(ScoringRules::Japan.run_score(&run(60, 10, 2)), 10 * SECOND);
+    }
+
+    #[test]
+    fn apec_adds_the_crash_penalty() {
+        assert_eq!(ScoringRules::Apec.run_score(&run(60, 10, 2)), 18 * SECOND);
+    }
+
+    #[test]
+    fn score_is_the_best_run() {
+        let runs = [run(0, 20, 0), run(60, 15, 0), run(300, 14, 0)];
+
+        assert_eq!(ScoringRules::Uk.score(&runs), Some(17 * SECOND));
+        assert_eq!(ScoringRules::Japan.score(&runs), Some(14 * SECOND));
+        assert_eq!(ScoringRules::Uk.score(&[]), None);
+    }
+}
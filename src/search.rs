@@ -0,0 +1,23 @@
+use std::cmp::Ordering;
+
+/// Search state of the solver and estimator, an index into their cost tables
+#[derive(Copy, Clone, PartialEq)]
+pub struct State {
+    pub cost: f64,
+    pub index: usize,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so the binary heap pops the cheapest state first
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex, RwLock, RwLockReadGuard,
+};
+
+use crate::maze::Maze;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MazeChange {
+    /// Walls or markers of the current maze changed
+    Edited,
+    /// A different maze replaced the current one
+    Loaded,
+}
+
+/// Maze shared by the environment, the sensors and the display
+#[derive(Clone)]
+pub struct SharedMaze<const R: usize, const C: usize> {
+    maze: Arc<RwLock<Maze<R, C>>>,
+    revision: Arc<AtomicU64>,
+    listeners: Arc<Mutex<Vec<Sender<MazeChange>>>>,
+}
+
+impl<const R: usize, const C: usize> SharedMaze<R, C> {
+    pub fn new(maze: Maze<R, C>) -> Self {
+        Self {
+            maze: Arc::new(RwLock::new(maze)),
+            revision: Arc::new(AtomicU64::new(0)),
+            listeners: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Maze<R, C>> {
+        self.maze.read().unwrap()
+    }
+
+    pub fn get_maze(&self) -> Maze<R, C> {
+        self.read().clone()
+    }
+
+    /// Increases with every change, so readers can tell when a copy is stale
+    pub fn get_revision(&self) -> u64 {
+        self.revision.load(Ordering::Acquire)
+    }
+
+    pub fn update<T>(&self, f: impl FnOnce(&mut Maze<R, C>) -> T) -> T {
+        let result = f(&mut self.maze.write().unwrap());
+
+        self.notify(MazeChange::Edited);
+
+        result
+    }
+
+    pub fn replace(&self, maze: Maze<R, C>) {
+        *self.maze.write().unwrap() = maze;
+
+        self.notify(MazeChange::Loaded);
+    }
+
+    /// Returns a receiver of every change made after this call
+    pub fn subscribe(&self) -> Receiver<MazeChange> {
+        let (change_tx, change_rx) = mpsc::channel();
+
+        self.listeners.lock().unwrap().push(change_tx);
+
+        change_rx
+    }
+
+    fn notify(&self, change: MazeChange) {
+        self.revision.fetch_add(1, Ordering::AcqRel);
+
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|change_tx| change_tx.send(change).is_ok());
+    }
+}
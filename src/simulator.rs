@@ -1,7 +1,18 @@
+use anyhow::Result;
+use maze_simulator_protocol::{ButtonsState, MazeRunnerRequest, MazeRunnerResponse, SOCKET};
 use pix_engine::prelude::Engine;
-use std::{sync::mpsc, thread};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
 use crate::{
+    battery::BatteryModel,
     communication::SimCommunication,
     distance_sensors::{
         DistanceSensorDiagonalLeft, DistanceSensorDiagonalRight, DistanceSensorFrontLeft,
@@ -9,7 +20,13 @@ use crate::{
     },
     engine::SimEngine,
     environment::SimEnvironment,
-    maze::Maze,
+    maze::{Cell, GoalArea, Maze},
+    panel::SimPanel,
+    position::Position,
+    run::RunLog,
+    runner::MazeOrientation,
+    scoring::ScoringRules,
+    shared_maze::SharedMaze,
     velocity::VelocityEnvironment,
     COLS, ROWS,
 };
@@ -29,53 +46,300 @@ pub const APP_HEIGHT: u32 = CELL_SIZE_VIS as u32 * ROWS as u32 + WALL_WIDTH_VIS
 pub const APP_WIDTH: u32 =
     CELL_SIZE_VIS as u32 * COLS as u32 + WALL_WIDTH_VIS as u32 + PANEL_WIDTH as u32;
 
-pub struct MazeSimulator<const R: usize, const C: usize>;
+pub struct MazeSimulatorBuilder<const R: usize, const C: usize> {
+    maze: Maze<R, C>,
+    socket: PathBuf,
+    mazefile: Option<PathBuf>,
+    maze_directory: Option<PathBuf>,
+    reset_on_crash: bool,
+    rules: ScoringRules,
+    maze_time_limit: Option<Duration>,
+    led_count: Option<usize>,
+    battery: Option<BatteryModel>,
+    start: Option<Cell<R, C>>,
+    goal: Option<GoalArea>,
+}
+
+impl<const R: usize, const C: usize> MazeSimulatorBuilder<R, C> {
+    pub fn new(maze: Maze<R, C>) -> Self {
+        Self {
+            maze,
+            socket: PathBuf::from(SOCKET),
+            mazefile: None,
+            maze_directory: None,
+            reset_on_crash: false,
+            rules: ScoringRules::default(),
+            maze_time_limit: None,
+            led_count: None,
+            battery: None,
+            start: None,
+            goal: None,
+        }
+    }
+
+    pub fn socket<P: Into<PathBuf>>(mut self, socket: P) -> Self {
+        self.socket = socket.into();
+        self
+    }
+
+    /// Mazefile the in-window editor saves to
+    pub fn mazefile<P: Into<PathBuf>>(mut self, mazefile: P) -> Self {
+        self.mazefile = Some(mazefile.into());
+        self
+    }
+
+    /// Directory listed by the maze browser, the mazefile directory by default
+    pub fn maze_directory<P: Into<PathBuf>>(mut self, maze_directory: P) -> Self {
+        self.maze_directory = Some(maze_directory.into());
+        self
+    }
+
+    pub fn reset_on_crash(mut self, reset_on_crash: bool) -> Self {
+        self.reset_on_crash = reset_on_crash;
+        self
+    }
+
+    pub fn rules(mut self, rules: ScoringRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn maze_time_limit(mut self, maze_time_limit: Duration) -> Self {
+        self.maze_time_limit = Some(maze_time_limit);
+        self
+    }
+
+    pub fn led_count(mut self, led_count: usize) -> Self {
+        self.led_count = Some(led_count);
+        self
+    }
+
+    pub fn battery(mut self, battery: BatteryModel) -> Self {
+        self.battery = Some(battery);
+        self
+    }
+
+    /// Start cell overriding the marker of this and every mazefile loaded later
+    pub fn start_cell(mut self, start: Cell<R, C>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Goal area overriding the markers of this and every mazefile loaded later
+    pub fn goal_area(mut self, goal: GoalArea) -> Self {
+        self.goal = Some(goal);
+        self
+    }
+
+    pub fn build(mut self) -> Result<MazeSimulator<R, C>> {
+        if let Some(start) = self.start {
+            self.maze.set_start_cell(start);
+        }
+
+        if let Some(goal) = self.goal {
+            self.maze.set_goal_area(goal)?;
+        }
+
+        let mut environment = SimEnvironment::new(self.maze)?;
+
+        environment.set_reset_on_crash(self.reset_on_crash);
+
+        if let Some(led_count) = self.led_count {
+            environment.set_led_count(led_count);
+        }
+
+        if let Some(battery) = self.battery {
+            environment.set_battery_model(battery);
+        }
+
+        {
+            let run_log = environment.get_run_log_handle();
+            let mut run_log = run_log.lock().unwrap();
+
+            run_log.set_rules(self.rules);
+            run_log.set_maze_time_limit(
+                self.maze_time_limit
+                    .unwrap_or_else(|| self.rules.default_maze_time()),
+            );
+        }
+
+        let maze = environment.get_maze_handle();
+        let runner_position = environment.get_runner_position_handle();
+        let distance_sensors = environment.get_distance_sensors_handle();
+        let velocity = environment.get_velocity_handle();
+
+        let distance_senors_environment =
+            DistanceSensorsEnvironment::<
+                R,
+                C,
+                DistanceSensorFrontLeft,
+                DistanceSensorFrontRight,
+                DistanceSensorDiagonalLeft,
+                DistanceSensorDiagonalRight,
+            >::new(maze, runner_position.clone(), distance_sensors);
+
+        let velocity_environment = VelocityEnvironment::new(runner_position, velocity);
+
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let workers = Workers {
+            stop: stop.clone(),
+            handles: vec![
+                {
+                    let stop = stop.clone();
+
+                    thread::spawn(move || distance_senors_environment.process(stop))
+                },
+                thread::spawn(move || velocity_environment.process(stop)),
+            ],
+        };
+
+        let maze_directory = self.maze_directory.or_else(|| {
+            self.mazefile
+                .as_ref()
+                .and_then(|mazefile| mazefile.parent())
+                .map(|directory| directory.to_path_buf())
+        });
+
+        Ok(MazeSimulator {
+            socket: self.socket,
+            mazefile: self.mazefile,
+            maze_directory,
+            start: self.start,
+            goal: self.goal,
+            environment,
+            workers,
+        })
+    }
+}
+
+/// Sensor and velocity threads, stopped and joined when the simulator is dropped
+struct Workers {
+    stop: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<Result<()>>>,
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        for handle in self.handles.drain(..) {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => println!("{e:#}"),
+                Err(_) => println!("Simulator thread panicked"),
+            }
+        }
+    }
+}
+
+pub struct MazeSimulator<const R: usize, const C: usize> {
+    socket: PathBuf,
+    mazefile: Option<PathBuf>,
+    maze_directory: Option<PathBuf>,
+    start: Option<Cell<R, C>>,
+    goal: Option<GoalArea>,
+    environment: SimEnvironment<R, C>,
+    workers: Workers,
+}
 
 impl<const R: usize, const C: usize> MazeSimulator<R, C> {
-    pub fn run(maze: Maze<R, C>) -> anyhow::Result<()> {
+    pub fn builder(maze: Maze<R, C>) -> MazeSimulatorBuilder<R, C> {
+        MazeSimulatorBuilder::new(maze)
+    }
+
+    /// Started moves finish and continuous moves come to a stop before returning, as nothing
+    /// else drives them in process
+    pub fn request(&mut self, request: MazeRunnerRequest) -> Result<MazeRunnerResponse> {
+        let response = self.environment.process_request(request)?;
+
+        self.environment.settle()?;
+
+        Ok(response)
+    }
+
+    pub fn press_buttons(&self, buttons: ButtonsState) {
+        self.environment
+            .get_buttons_handle()
+            .lock()
+            .unwrap()
+            .insert(buttons);
+    }
+
+    pub fn get_maze(&self) -> Maze<R, C> {
+        self.environment.get_maze_handle().get_maze()
+    }
+
+    pub fn get_maze_handle(&self) -> SharedMaze<R, C> {
+        self.environment.get_maze_handle()
+    }
+
+    pub fn get_runner_cell(&self) -> Cell<R, C> {
+        self.environment.get_runner().get_cell()
+    }
+
+    pub fn get_runner_orientation(&self) -> MazeOrientation {
+        self.environment.get_runner().get_orientation()
+    }
+
+    pub fn get_run_log(&self) -> Arc<Mutex<RunLog<R, C>>> {
+        self.environment.get_run_log_handle()
+    }
+
+    pub fn get_runner_position(&self) -> Position<R> {
+        self.environment
+            .get_runner_position_handle()
+            .lock()
+            .unwrap()
+            .clone()
+    }
+
+    pub fn run(self) -> Result<()> {
         let (request_tx, request_rx) = mpsc::channel();
         let (response_tx, response_rx) = mpsc::channel();
 
-        let environment = SimEnvironment::new(maze.clone(), request_rx, response_tx)?;
+        let environment = self.environment;
 
+        // The window needs the sensor and velocity threads until it is closed
+        let _workers = self.workers;
+
+        let maze = environment.get_maze_handle();
         let runner_position = environment.get_runner_position_handle();
         let buttons = environment.get_buttons_handle();
+        let outputs = environment.get_outputs_handle();
+        let battery = environment.get_battery_handle();
         let runner_context = environment.get_runner_context_handle();
         let distance_sensors = environment.get_distance_sensors_handle();
-        let velocity = environment.get_velocity_handle();
-
-        let _ = thread::spawn(move || environment.process().unwrap());
-
-        let communication = SimCommunication::new(request_tx, response_rx)?;
+        let run_log = environment.get_run_log_handle();
 
-        let _ = thread::spawn(move || communication.process().unwrap());
+        let _ = thread::spawn(move || {
+            if let Err(e) = environment.process(request_rx, response_tx) {
+                println!("Environment stopped: {e:#}");
+            }
+        });
 
-        let distance_senors_environment = DistanceSensorsEnvironment::<
-            R,
-            C,
-            DistanceSensorFrontLeft,
-            DistanceSensorFrontRight,
-            DistanceSensorDiagonalLeft,
-            DistanceSensorDiagonalRight,
-        >::new(
-            maze.clone(),
-            runner_position.clone(),
-            distance_sensors.clone(),
-        );
+        let events = request_tx.clone();
 
-        let _ = thread::spawn(move || distance_senors_environment.process().unwrap());
+        let communication = SimCommunication::new(&self.socket, request_tx, response_rx)?;
 
-        let velocity_environment = VelocityEnvironment::new(runner_position.clone(), velocity);
+        let _ = thread::spawn(move || {
+            if let Err(e) = communication.process() {
+                println!("Communication stopped: {e:#}");
+            }
+        });
 
-        let _ = thread::spawn(move || velocity_environment.process().unwrap());
+        let panel = SimPanel::new(buttons, outputs, battery, distance_sensors.clone(), run_log);
 
-        let mut engine = SimEngine::<R, C, _, _, _>::new(
+        let mut engine = SimEngine::<R, C, _, _>::new(
             maze,
             runner_position,
-            buttons,
+            panel,
             runner_context,
             distance_sensors,
-        );
+        )
+        .mazefile(self.mazefile, self.maze_directory)
+        .markers(self.start, self.goal)
+        .events(events);
 
         let mut pix_engine = Engine::builder()
             .dimensions(APP_WIDTH + 1, APP_HEIGHT + 1)
@@ -0,0 +1,456 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use pix_engine::prelude::*;
+use std::{collections::BinaryHeap, f64::consts::PI, time::Duration};
+
+use crate::{
+    engine::Render,
+    maze::{Cell, CellState, Maze},
+    position::Millimeters,
+    runner::MazeOrientation,
+    search::State,
+    simulator::{CELL_SIZE_MM, CELL_SIZE_VIS, WALL_WIDTH_VIS},
+};
+
+// Cost of a turn for the turn-weighted metric, in cells
+const TURN_COST_CELLS: f64 = 2.0;
+// Small enough to only decide between paths with the same number of cells
+const TURN_TIE_BREAK: f64 = 1e-4;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ValueEnum)]
+pub enum PathMetric {
+    /// Fewest cells, then fewest turns
+    #[default]
+    Cells,
+    /// Fewest cells, with every turn counting as two extra cells
+    Turns,
+    /// Shortest time with the run profile
+    Time,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RunProfile {
+    /// Top speed on straights [mm/s]
+    pub max_speed: f64,
+    /// Acceleration and deceleration on straights [mm/s^2]
+    pub acceleration: f64,
+    /// Speed through smooth 90 degree turns [mm/s]
+    pub turn_speed: f64,
+}
+
+impl Default for RunProfile {
+    fn default() -> Self {
+        Self {
+            max_speed: 2000.0,
+            acceleration: 5000.0,
+            turn_speed: 500.0,
+        }
+    }
+}
+
+impl RunProfile {
+    /// Time of a trapezoidal profile starting at `start_speed` and ending at most at `end_speed`
+    pub fn straight_time(&self, distance: Millimeters, start_speed: f64, end_speed: f64) -> f64 {
+        trapezoid_time(
+            distance,
+            start_speed,
+            end_speed,
+            self.max_speed,
+            self.acceleration,
+        )
+    }
+
+    /// Time of a 90 degree turn on an arc through the center of the cell
+    pub fn turn_time(&self) -> f64 {
+        PI / 2.0 * turn_radius() / self.turn_speed
+    }
+}
+
+/// Time to cover `distance` starting at `start_speed` and ending at most at `end_speed`
+pub fn trapezoid_time(
+    distance: Millimeters,
+    start_speed: f64,
+    end_speed: f64,
+    max_speed: f64,
+    acceleration: f64,
+) -> f64 {
+    if distance <= 0.0 {
+        return 0.0;
+    }
+
+    let a = acceleration;
+
+    let peak_speed = ((2.0 * a * distance + start_speed.powi(2) + end_speed.powi(2)) / 2.0)
+        .sqrt()
+        .min(max_speed);
+
+    if peak_speed <= end_speed {
+        let top_speed = (start_speed.powi(2) + 2.0 * a * distance)
+            .sqrt()
+            .min(max_speed);
+
+        let accelerating = (top_speed.powi(2) - start_speed.powi(2)) / (2.0 * a);
+
+        return (top_speed - start_speed) / a + (distance - accelerating) / top_speed;
+    }
+
+    let accelerating = (peak_speed.powi(2) - start_speed.powi(2)) / (2.0 * a);
+    let decelerating = (peak_speed.powi(2) - end_speed.powi(2)) / (2.0 * a);
+
+    (peak_speed - start_speed) / a
+        + (peak_speed - end_speed) / a
+        + (distance - accelerating - decelerating) / peak_speed
+}
+
+fn turn_radius() -> Millimeters {
+    CELL_SIZE_MM as f64 / 2.0
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution<const R: usize, const C: usize> {
+    /// Cells from the start cell to the first goal cell reached
+    pub cells: Vec<Cell<R, C>>,
+    pub turns: usize,
+    pub time: Duration,
+}
+
+impl<const R: usize, const C: usize> Solution<R, C> {
+    pub fn get_cell_count(&self) -> usize {
+        self.cells.len().saturating_sub(1)
+    }
+}
+
+/// Reference solver, a weighted flood fill over straight segments between turns
+pub struct MazeSolver<'a, const R: usize, const C: usize> {
+    maze: &'a Maze<R, C>,
+    profile: RunProfile,
+}
+
+impl<'a, const R: usize, const C: usize> MazeSolver<'a, R, C> {
+    pub fn new(maze: &'a Maze<R, C>) -> Self {
+        Self {
+            maze,
+            profile: RunProfile::default(),
+        }
+    }
+
+    pub fn profile(mut self, profile: RunProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// States are a cell and the heading the runner entered it with, the start state has none
+    fn index(cell: Cell<R, C>, heading: Option<usize>) -> usize {
+        (cell.x * R + cell.y) * 5 + heading.unwrap_or(4)
+    }
+
+    fn state(index: usize) -> (Cell<R, C>, Option<usize>) {
+        let heading = index % 5;
+        let cell = index / 5;
+
+        (
+            Cell {
+                x: cell / R,
+                y: cell % R,
+            },
+            (heading < 4).then_some(heading),
+        )
+    }
+
+    fn segment_cost(
+        &self,
+        metric: PathMetric,
+        heading: Option<usize>,
+        next_heading: usize,
+        length: usize,
+        reaches_goal: bool,
+    ) -> f64 {
+        let turn = heading.is_some_and(|heading| heading != next_heading);
+
+        match metric {
+            PathMetric::Cells => length as f64 + if turn { TURN_TIE_BREAK } else { 0.0 },
+            PathMetric::Turns => length as f64 + if turn { TURN_COST_CELLS } else { 0.0 },
+            PathMetric::Time => self.segment_time(heading.is_none(), turn, length, reaches_goal),
+        }
+    }
+
+    /// Straights run between cell centers, turns cut half a cell off the straights on both sides
+    fn segment_time(&self, first: bool, turn: bool, length: usize, reaches_goal: bool) -> f64 {
+        let mut distance = length as f64 * CELL_SIZE_MM as f64;
+
+        if !first {
+            distance -= turn_radius();
+        }
+
+        if !reaches_goal {
+            distance -= turn_radius();
+        }
+
+        let start_speed = if first { 0.0 } else { self.profile.turn_speed };
+
+        let end_speed = if reaches_goal {
+            self.profile.max_speed
+        } else {
+            self.profile.turn_speed
+        };
+
+        let turn_time = if turn { self.profile.turn_time() } else { 0.0 };
+
+        turn_time + self.profile.straight_time(distance, start_speed, end_speed)
+    }
+
+    pub fn solve(&self, metric: PathMetric) -> Option<Solution<R, C>> {
+        let start = self.maze.get_start_cell();
+
+        let mut costs = vec![f64::INFINITY; C * R * 5];
+        let mut previous = vec![None; C * R * 5];
+        let mut heap = BinaryHeap::new();
+
+        let start_index = Self::index(start, None);
+
+        costs[start_index] = 0.0;
+        heap.push(State {
+            cost: 0.0,
+            index: start_index,
+        });
+
+        let mut best = None;
+
+        while let Some(State { cost, index }) = heap.pop() {
+            if cost > costs[index] {
+                continue;
+            }
+
+            let (cell, heading) = Self::state(index);
+
+            // States are popped cheapest first, so the first goal cell ends the search
+            if self.maze.is_goal_cell(cell) {
+                best = Some(index);
+
+                break;
+            }
+
+            for next_heading in 0..4 {
+                let wall = heading_wall(next_heading);
+
+                // Straights are single segments and turning back never shortens a path
+                if heading.is_some_and(|heading| heading % 2 == next_heading % 2) {
+                    continue;
+                }
+
+                let mut next = cell;
+                let mut length = 0;
+
+                // Every reachable cell along the straight ends a segment
+                while !self.maze.get_cell_state(next).contains(wall) {
+                    let Some(following) = self.maze.get_neighbour(next, wall) else {
+                        break;
+                    };
+
+                    next = following;
+                    length += 1;
+
+                    let reaches_goal = self.maze.is_goal_cell(next);
+
+                    let next_index = Self::index(next, Some(next_heading));
+                    let next_cost = cost
+                        + self.segment_cost(metric, heading, next_heading, length, reaches_goal);
+
+                    if next_cost < costs[next_index] {
+                        costs[next_index] = next_cost;
+                        previous[next_index] = Some(index);
+
+                        heap.push(State {
+                            cost: next_cost,
+                            index: next_index,
+                        });
+                    }
+
+                    if reaches_goal {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut corners = vec![best?];
+
+        while let Some(index) = previous[*corners.last().unwrap()] {
+            corners.push(index);
+        }
+
+        corners.reverse();
+
+        Some(self.expand(&corners))
+    }
+
+    /// Fills in the cells between the ends of every segment and measures the path
+    fn expand(&self, corners: &[usize]) -> Solution<R, C> {
+        let mut cells = vec![Self::state(corners[0]).0];
+        let mut turns = 0;
+        let mut time = 0.0;
+
+        for (i, pair) in corners.windows(2).enumerate() {
+            let (from, heading) = Self::state(pair[0]);
+            let (to, next_heading) = Self::state(pair[1]);
+
+            let next_heading = next_heading.expect("Only the start state has no heading");
+            let wall = heading_wall(next_heading);
+
+            let turn = heading.is_some_and(|heading| heading != next_heading);
+
+            if turn {
+                turns += 1;
+            }
+
+            let mut cell = from;
+            let mut length = 0;
+
+            while cell != to {
+                cell = self
+                    .maze
+                    .get_neighbour(cell, wall)
+                    .expect("Segment should stay in the maze");
+                cells.push(cell);
+                length += 1;
+            }
+
+            time += self.segment_time(i == 0, turn, length, i + 2 == corners.len());
+        }
+
+        Solution {
+            cells,
+            turns,
+            time: Duration::from_secs_f64(time),
+        }
+    }
+}
+
+/// Wall crossed going along a heading, clockwise from north in quarter turns
+fn heading_wall(heading: usize) -> CellState {
+    MazeOrientation::from_eighths(2 * heading)
+        .get_wall()
+        .expect("Solver headings are orthogonal")
+}
+
+impl<const R: usize, const C: usize> Render for Solution<R, C> {
+    fn draw<T>(&self, s: &mut PixState, primary_color: T, secondary_color: T) -> Result<()>
+    where
+        T: Into<Option<Color>> + std::marker::Copy,
+    {
+        let center = |cell: &Cell<R, C>| {
+            point![
+                cell.x as i32 * CELL_SIZE_VIS + (CELL_SIZE_VIS + WALL_WIDTH_VIS) / 2,
+                (R - cell.y - 1) as i32 * CELL_SIZE_VIS + (CELL_SIZE_VIS + WALL_WIDTH_VIS) / 2
+            ]
+        };
+
+        s.stroke(primary_color);
+        s.stroke_weight(2);
+
+        for pair in self.cells.windows(2) {
+            s.line(line_![center(&pair[0]), center(&pair[1])])?;
+        }
+
+        s.stroke_weight(1);
+        s.stroke(secondary_color);
+        s.fill(secondary_color);
+
+        for cell in self.cells.first().into_iter().chain(self.cells.last()) {
+            s.circle(circle![center(cell), 3])?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open 4x4 maze with outer walls, the start at (0, 0) only open to the north
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for i in 0..4 {
+            maze.update_cell_state(Cell { x: i, y: 0 }, CellState::SouthWall, true);
+            maze.update_cell_state(Cell { x: i, y: 3 }, CellState::NorthWall, true);
+            maze.update_cell_state(Cell { x: 0, y: i }, CellState::WestWall, true);
+            maze.update_cell_state(Cell { x: 3, y: i }, CellState::EastWall, true);
+        }
+
+        maze.update_cell_state(Cell { x: 0, y: 0 }, CellState::EastWall, true);
+
+        maze.set_start_cell(Cell { x: 0, y: 0 });
+        maze.set_goal_cell(Cell { x: 3, y: 3 });
+
+        maze
+    }
+
+    fn cells(solution: &Solution<4, 4>) -> Vec<(usize, usize)> {
+        solution.cells.iter().map(|cell| (cell.x, cell.y)).collect()
+    }
+
+    #[test]
+    fn shortest_path_goes_around_walls() {
+        let mut maze = maze();
+
+        // Only the east column leads north from the second row
+        for x in 0..3 {
+            maze.update_cell_state(Cell { x, y: 1 }, CellState::NorthWall, true);
+        }
+
+        let solution = MazeSolver::new(&maze).solve(PathMetric::Cells).unwrap();
+
+        assert_eq!(
+            cells(&solution),
+            vec![(0, 0), (0, 1), (1, 1), (2, 1), (3, 1), (3, 2), (3, 3)]
+        );
+        assert_eq!(solution.get_cell_count(), 6);
+        assert_eq!(solution.turns, 2);
+    }
+
+    #[test]
+    fn cells_metric_prefers_fewer_turns() {
+        let solution = MazeSolver::new(&maze()).solve(PathMetric::Cells).unwrap();
+
+        assert_eq!(solution.get_cell_count(), 6);
+        assert_eq!(solution.turns, 1);
+    }
+
+    #[test]
+    fn straight_path_takes_the_straight_time() {
+        let mut maze = maze();
+
+        maze.clear_goal_cell(Cell { x: 3, y: 3 });
+        maze.set_goal_cell(Cell { x: 0, y: 3 });
+
+        let profile = RunProfile::default();
+
+        let solution = MazeSolver::new(&maze)
+            .profile(profile)
+            .solve(PathMetric::Time)
+            .unwrap();
+
+        assert_eq!(cells(&solution), vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(solution.turns, 0);
+
+        let expected = profile.straight_time(3.0 * CELL_SIZE_MM as f64, 0.0, profile.max_speed);
+
+        assert!((solution.time.as_secs_f64() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn walled_off_goal_has_no_path() {
+        let mut maze = maze();
+
+        let goal = Cell { x: 3, y: 3 };
+
+        maze.update_cell_state(goal, CellState::SouthWall, true);
+        maze.update_cell_state(goal, CellState::WestWall, true);
+
+        for metric in [PathMetric::Cells, PathMetric::Turns, PathMetric::Time] {
+            assert!(MazeSolver::new(&maze).solve(metric).is_none());
+        }
+    }
+}
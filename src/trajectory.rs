@@ -0,0 +1,343 @@
+use anyhow::{bail, Result};
+
+use crate::{
+    maze::{Cell, CellState, Maze},
+    position::{Angle, Millimeters, Position, Radians, RUNNER_SIZE_MM},
+    simulator::{CELL_SIZE_MM, WALL_WIDTH_MM},
+};
+
+// Distance between the poses checked against the walls
+const SAMPLE_STEP_MM: Millimeters = 5.0;
+
+// The body is taken as a circle of its half width
+const CLEARANCE_MM: Millimeters = RUNNER_SIZE_MM / 2.0 + WALL_WIDTH_MM as f64 / 2.0;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Segment {
+    Straight(Millimeters),
+    /// Arc with the given radius, positive angles turn left
+    Arc(Millimeters, Radians),
+    /// Rotation in place, positive angles turn left
+    Spin(Radians),
+}
+
+impl Segment {
+    /// Pose after covering `fraction` of the segment from `start`
+    pub fn advance<const R: usize>(&self, start: &Position<R>, fraction: f64) -> Position<R> {
+        let theta = start.theta.as_radians();
+
+        match *self {
+            Segment::Straight(length) => Position::new(
+                start.x + length * fraction * theta.cos(),
+                start.y + length * fraction * theta.sin(),
+                start.theta,
+            ),
+            Segment::Arc(radius, angle) => {
+                let side = angle.signum();
+                let angle = angle * fraction;
+
+                let center_x = start.x - side * radius * theta.sin();
+                let center_y = start.y + side * radius * theta.cos();
+
+                Position::new(
+                    center_x + side * radius * (theta + angle).sin(),
+                    center_y - side * radius * (theta + angle).cos(),
+                    Angle::radians(theta + angle),
+                )
+            }
+            Segment::Spin(angle) => {
+                Position::new(start.x, start.y, Angle::radians(theta + angle * fraction))
+            }
+        }
+    }
+
+    pub fn get_length(&self) -> Millimeters {
+        match *self {
+            Segment::Straight(length) => length,
+            Segment::Arc(radius, angle) => radius * angle.abs(),
+            Segment::Spin(_) => 0.0,
+        }
+    }
+}
+
+/// Path of the runner's center, built from straights, arcs and rotations in place
+#[derive(Clone)]
+pub struct Trajectory<const R: usize> {
+    start: Position<R>,
+    segments: Vec<Segment>,
+}
+
+impl<const R: usize> Trajectory<R> {
+    pub fn new(start: Position<R>) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn straight(mut self, length: Millimeters) -> Self {
+        self.segments.push(Segment::Straight(length));
+        self
+    }
+
+    pub fn spin(mut self, angle: Radians) -> Self {
+        self.segments.push(Segment::Spin(angle));
+        self
+    }
+
+    /// Straights through the waypoints, every corner rounded with the largest arc that fits
+    pub fn through(mut self, waypoints: &[(Millimeters, Millimeters)]) -> Self {
+        let end = self.get_end();
+
+        let mut points = vec![(end.x, end.y)];
+        points.extend_from_slice(waypoints);
+
+        let legs: Vec<(Millimeters, Radians)> = points
+            .windows(2)
+            .map(|pair| {
+                let (dx, dy) = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+
+                (dx.hypot(dy), dy.atan2(dx))
+            })
+            .collect();
+
+        // Legs between two corners are shared by both of their arcs
+        let available = |leg: usize| {
+            let shared = leg > 0 && leg + 1 < legs.len();
+
+            legs[leg].0 / if shared { 2.0 } else { 1.0 }
+        };
+
+        let corners: Vec<(Millimeters, Radians)> = (1..legs.len())
+            .map(|leg| {
+                let deflection = Angle::radians(legs[leg].1 - legs[leg - 1].1).as_radians();
+                let tangent = available(leg - 1).min(available(leg));
+
+                (tangent, deflection)
+            })
+            .collect();
+
+        for (leg, (length, _)) in legs.iter().enumerate() {
+            let before = leg.checked_sub(1).map_or(0.0, |corner| corners[corner].0);
+            let after = corners.get(leg).map_or(0.0, |corner| corner.0);
+
+            let straight = length - before - after;
+
+            if straight > 1e-6 {
+                self.segments.push(Segment::Straight(straight));
+            }
+
+            if let Some(&(tangent, deflection)) = corners.get(leg) {
+                let radius = tangent / (deflection.abs() / 2.0).tan();
+
+                self.segments.push(Segment::Arc(radius, deflection));
+            }
+        }
+
+        self
+    }
+
+    pub fn get_start(&self) -> Position<R> {
+        self.start.clone()
+    }
+
+    pub fn get_end(&self) -> Position<R> {
+        self.segments
+            .iter()
+            .fold(self.start.clone(), |pose, segment| {
+                segment.advance(&pose, 1.0)
+            })
+    }
+
+    /// Every segment with the pose it ends in
+    pub fn get_steps(&self) -> Vec<(Segment, Position<R>)> {
+        let mut pose = self.start.clone();
+
+        self.segments
+            .iter()
+            .map(|segment| {
+                pose = segment.advance(&pose, 1.0);
+
+                (*segment, pose.clone())
+            })
+            .collect()
+    }
+
+    pub fn get_length(&self) -> Millimeters {
+        self.segments.iter().map(Segment::get_length).sum()
+    }
+
+    fn sample(&self) -> Vec<Position<R>> {
+        let mut poses = vec![self.start.clone()];
+
+        for segment in &self.segments {
+            let start = poses.last().unwrap().clone();
+            let count = (segment.get_length() / SAMPLE_STEP_MM).ceil().max(1.0) as usize;
+
+            poses.extend((1..=count).map(|i| segment.advance(&start, i as f64 / count as f64)));
+        }
+
+        poses
+    }
+
+    /// Checks that the runner keeps clear of every wall and post along the way
+    pub fn check_clearance<const C: usize>(&self, maze: &Maze<R, C>) -> Result<()> {
+        let obstacles = obstacles(maze);
+
+        for pose in self.sample() {
+            let hit = obstacles
+                .iter()
+                .any(|&(from, to)| distance_to_segment((pose.x, pose.y), from, to) < CLEARANCE_MM);
+
+            if hit {
+                bail!(
+                    "Runner hits a wall at ({:.0}, {:.0}) mm heading {:.0} deg",
+                    pose.x,
+                    pose.y,
+                    pose.theta.as_degrees()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+type Point = (Millimeters, Millimeters);
+
+/// Wall center lines and posts, posts as segments of zero length
+fn obstacles<const R: usize, const C: usize>(maze: &Maze<R, C>) -> Vec<(Point, Point)> {
+    let size = CELL_SIZE_MM as f64;
+    let mut obstacles = Vec::new();
+
+    for x in 0..C {
+        for y in 0..R {
+            let state = maze.get_cell_state(Cell { x, y });
+
+            let (x0, y0) = (x as f64 * size, y as f64 * size);
+            let (x1, y1) = (x0 + size, y0 + size);
+
+            let walls = [
+                (CellState::NorthWall, (x0, y1), (x1, y1)),
+                (CellState::EastWall, (x1, y0), (x1, y1)),
+                (CellState::SouthWall, (x0, y0), (x1, y0)),
+                (CellState::WestWall, (x0, y0), (x0, y1)),
+            ];
+
+            for (wall, from, to) in walls {
+                if state.contains(wall) {
+                    obstacles.push((from, to));
+                }
+            }
+        }
+    }
+
+    for x in 0..=C {
+        for y in 0..=R {
+            let post = (x as f64 * size, y as f64 * size);
+
+            obstacles.push((post, post));
+        }
+    }
+
+    obstacles
+}
+
+fn distance_to_segment(point: Point, from: Point, to: Point) -> Millimeters {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx * dx + dy * dy;
+
+    let t = if length > 0.0 {
+        (((point.0 - from.0) * dx + (point.1 - from.1) * dy) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    (point.0 - from.0 - t * dx).hypot(point.1 - from.1 - t * dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x4 maze with only its outer walls
+    fn open_maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for i in 0..4 {
+            maze.update_cell_state(Cell { x: i, y: 0 }, CellState::SouthWall, true);
+            maze.update_cell_state(Cell { x: i, y: 3 }, CellState::NorthWall, true);
+            maze.update_cell_state(Cell { x: 0, y: i }, CellState::WestWall, true);
+            maze.update_cell_state(Cell { x: 3, y: i }, CellState::EastWall, true);
+        }
+
+        maze
+    }
+
+    /// Walls everywhere except inside the 2x2 area of cells (1, 1) to (2, 2)
+    fn area_maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for x in 0..4 {
+            for y in 0..4 {
+                maze.set_cell_state(Cell { x, y }, CellState::all() - CellState::Visited);
+            }
+        }
+
+        for (x, y) in [(1, 1), (1, 2)] {
+            maze.update_cell_state(Cell { x, y }, CellState::EastWall, false);
+        }
+
+        for (x, y) in [(1, 1), (2, 1)] {
+            maze.update_cell_state(Cell { x, y }, CellState::NorthWall, false);
+        }
+
+        maze
+    }
+
+    fn pose(x: Millimeters, y: Millimeters, theta: f64) -> Position<4> {
+        Position::new(x, y, Angle::degrees(theta))
+    }
+
+    #[test]
+    fn diagonal_past_a_lone_post_is_blocked() {
+        // From the center of (1, 1) to the center of (2, 2), over the post between them
+        let trajectory = Trajectory::new(pose(270.0, 270.0, 45.0)).through(&[(450.0, 450.0)]);
+
+        assert!(trajectory.check_clearance(&area_maze()).is_err());
+    }
+
+    #[test]
+    fn diagonal_through_an_open_area_is_allowed() {
+        // Through the midpoints of the walls missing around the post instead
+        let trajectory = Trajectory::new(pose(270.0, 270.0, 90.0)).through(&[
+            (270.0, 360.0),
+            (360.0, 450.0),
+            (450.0, 450.0),
+        ]);
+
+        assert!(trajectory.check_clearance(&area_maze()).is_ok());
+    }
+
+    #[test]
+    fn turn_arc_clipping_a_wall_end_is_blocked() {
+        let mut maze = open_maze();
+
+        maze.update_cell_state(Cell { x: 1, y: 2 }, CellState::EastWall, true);
+
+        // Search turn around the post at the corner of (1, 1)
+        let search =
+            Trajectory::new(pose(270.0, 180.0, 90.0)).through(&[(270.0, 270.0), (360.0, 270.0)]);
+
+        assert!(search.check_clearance(&maze).is_ok());
+
+        // Wider arc passing the post the wall ends at, without crossing the wall itself
+        let wide =
+            Trajectory::new(pose(270.0, 180.0, 90.0)).through(&[(270.0, 360.0), (450.0, 360.0)]);
+
+        assert!(
+            matches!(wide.get_steps()[0].0, Segment::Arc(radius, _) if (radius - 180.0).abs() < 1e-6)
+        );
+        assert!(wide.check_clearance(&maze).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::maze::{Cell, CellState, GoalArea, Maze};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic<const R: usize, const C: usize> {
+    pub severity: Severity,
+    pub cell: Option<Cell<R, C>>,
+    pub message: String,
+}
+
+impl<const R: usize, const C: usize> fmt::Display for Diagnostic<R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        match self.cell {
+            Some(cell) => write!(
+                f,
+                "{}: ({}, {}): {}",
+                severity, cell.x, cell.y, self.message
+            ),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+pub struct MazeValidator<const R: usize, const C: usize> {
+    maze: Maze<R, C>,
+    diagnostics: Vec<Diagnostic<R, C>>,
+}
+
+impl<const R: usize, const C: usize> MazeValidator<R, C> {
+    pub fn new(maze: &Maze<R, C>) -> Self {
+        Self {
+            maze: maze.clone(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn validate(mut self) -> Vec<Diagnostic<R, C>> {
+        self.check_markers();
+        self.check_neighbour_walls();
+        self.check_boundary();
+        self.check_posts();
+        self.check_start();
+        self.check_goal();
+
+        self.diagnostics
+    }
+
+    fn report(&mut self, severity: Severity, cell: Option<Cell<R, C>>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            cell,
+            message,
+        });
+    }
+
+    fn has_wall(&self, x: usize, y: usize, wall: CellState) -> bool {
+        self.maze.get_cell_state(Cell { x, y }).contains(wall)
+    }
+
+    fn check_markers(&mut self) {
+        if !self.maze.has_start_cell() {
+            let start = self.maze.infer_start_cell();
+
+            self.report(
+                Severity::Warning,
+                None,
+                format!(
+                    "no start cell is marked, assuming ({}, {})",
+                    start.x, start.y
+                ),
+            );
+        }
+
+        if self.maze.get_goal_cells().is_empty() {
+            self.report(
+                Severity::Warning,
+                None,
+                "no goal cell is marked, assuming the center of the maze".to_string(),
+            );
+        }
+
+        self.maze
+            .infer_missing_markers(GoalArea::center::<R, C>())
+            .expect("Center goal area should be in bounds");
+    }
+
+    fn check_neighbour_walls(&mut self) {
+        for y in 0..R {
+            for x in 0..C {
+                if x + 1 < C
+                    && self.has_wall(x, y, CellState::EastWall)
+                        != self.has_wall(x + 1, y, CellState::WestWall)
+                {
+                    self.report(
+                        Severity::Error,
+                        Some(Cell { x, y }),
+                        format!("east wall disagrees with west wall of ({}, {})", x + 1, y),
+                    );
+                }
+
+                if y + 1 < R
+                    && self.has_wall(x, y, CellState::NorthWall)
+                        != self.has_wall(x, y + 1, CellState::SouthWall)
+                {
+                    self.report(
+                        Severity::Error,
+                        Some(Cell { x, y }),
+                        format!("north wall disagrees with south wall of ({}, {})", x, y + 1),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_boundary(&mut self) {
+        for x in 0..C {
+            if !self.has_wall(x, 0, CellState::SouthWall) {
+                self.report(
+                    Severity::Error,
+                    Some(Cell { x, y: 0 }),
+                    "outer south wall is missing".to_string(),
+                );
+            }
+
+            if !self.has_wall(x, R - 1, CellState::NorthWall) {
+                self.report(
+                    Severity::Error,
+                    Some(Cell { x, y: R - 1 }),
+                    "outer north wall is missing".to_string(),
+                );
+            }
+        }
+
+        for y in 0..R {
+            if !self.has_wall(0, y, CellState::WestWall) {
+                self.report(
+                    Severity::Error,
+                    Some(Cell { x: 0, y }),
+                    "outer west wall is missing".to_string(),
+                );
+            }
+
+            if !self.has_wall(C - 1, y, CellState::EastWall) {
+                self.report(
+                    Severity::Error,
+                    Some(Cell { x: C - 1, y }),
+                    "outer east wall is missing".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_posts(&mut self) {
+        // Every inner post except the one in the middle of the goal needs at least one wall
+        for y in 1..R {
+            for x in 1..C {
+                if self.maze.is_free_standing_post(x, y) {
+                    self.report(
+                        Severity::Warning,
+                        Some(Cell { x, y }),
+                        "post at the south-west corner has no walls attached".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_start(&mut self) {
+        let start = self.maze.get_start_cell();
+
+        if (start.x != 0 && start.x != C - 1) || (start.y != 0 && start.y != R - 1) {
+            self.report(
+                Severity::Warning,
+                Some(start),
+                "start cell is not in a corner".to_string(),
+            );
+        }
+
+        let openings = self.maze.get_accessible_neighbours(start).len();
+
+        if openings != 1 {
+            self.report(
+                Severity::Warning,
+                Some(start),
+                format!("start cell has {} openings, expected 1", openings),
+            );
+        }
+    }
+
+    fn check_goal(&mut self) {
+        let goal_cells = self.maze.get_goal_cells();
+
+        let reachable = self.reachable_cells();
+
+        if !goal_cells.iter().any(|cell| reachable[cell.x][cell.y]) {
+            self.report(
+                Severity::Error,
+                None,
+                "goal is not reachable from the start cell".to_string(),
+            );
+        }
+
+        let unreachable = reachable.iter().flatten().filter(|r| !**r).count();
+
+        if unreachable > 0 {
+            self.report(
+                Severity::Warning,
+                None,
+                format!(
+                    "{} cells are not reachable from the start cell",
+                    unreachable
+                ),
+            );
+        }
+    }
+
+    fn reachable_cells(&self) -> [[bool; R]; C] {
+        let mut reachable = [[false; R]; C];
+        let mut queue = VecDeque::new();
+
+        let start = self.maze.get_start_cell();
+
+        reachable[start.x][start.y] = true;
+        queue.push_back(start);
+
+        while let Some(cell) = queue.pop_front() {
+            for neighbour in self.maze.get_accessible_neighbours(cell) {
+                if !reachable[neighbour.x][neighbour.y] {
+                    reachable[neighbour.x][neighbour.y] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serpentine from the start at (0, 0) along every row up to the goal at (0, 3)
+    fn maze() -> Maze<4, 4> {
+        let mut maze = Maze::<4, 4>::new();
+
+        for x in 0..4 {
+            for y in 0..4 {
+                maze.set_cell_state(Cell { x, y }, CellState::all() - CellState::Visited);
+            }
+        }
+
+        for y in 0..4 {
+            for x in 0..3 {
+                maze.update_cell_state(Cell { x, y }, CellState::EastWall, false);
+            }
+        }
+
+        maze.update_cell_state(Cell { x: 3, y: 0 }, CellState::NorthWall, false);
+        maze.update_cell_state(Cell { x: 0, y: 1 }, CellState::NorthWall, false);
+        maze.update_cell_state(Cell { x: 3, y: 2 }, CellState::NorthWall, false);
+
+        maze.set_start_cell(Cell { x: 0, y: 0 });
+        maze.set_goal_cell(Cell { x: 0, y: 3 });
+
+        maze
+    }
+
+    fn has_diagnostic(
+        diagnostics: &[Diagnostic<4, 4>],
+        severity: Severity,
+        cell: Option<(usize, usize)>,
+        message: &str,
+    ) -> bool {
+        diagnostics.iter().any(|diagnostic| {
+            diagnostic.severity == severity
+                && diagnostic.cell.map(|cell| (cell.x, cell.y)) == cell
+                && diagnostic.message.contains(message)
+        })
+    }
+
+    #[test]
+    fn valid_maze_has_no_diagnostics() {
+        let diagnostics = MazeValidator::new(&maze()).validate();
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn missing_boundary_wall_is_an_error() {
+        let mut maze = maze();
+
+        maze.update_cell_state(Cell { x: 0, y: 2 }, CellState::WestWall, false);
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Error,
+            Some((0, 2)),
+            "outer west wall is missing"
+        ));
+    }
+
+    #[test]
+    fn neighbour_wall_mismatch_is_an_error() {
+        let mut maze = maze();
+
+        // Only one side of the wall, unlike `update_cell_state`
+        let cell = Cell { x: 1, y: 1 };
+
+        maze.set_cell_state(cell, maze.get_cell_state(cell) - CellState::NorthWall);
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Error,
+            Some((1, 1)),
+            "north wall disagrees with south wall of (1, 2)"
+        ));
+    }
+
+    #[test]
+    fn free_standing_post_is_a_warning() {
+        let mut maze = maze();
+
+        maze.update_cell_state(Cell { x: 1, y: 1 }, CellState::NorthWall, false);
+        maze.update_cell_state(Cell { x: 2, y: 1 }, CellState::NorthWall, false);
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Warning,
+            Some((2, 2)),
+            "post at the south-west corner has no walls attached"
+        ));
+        assert!(!diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error));
+    }
+
+    #[test]
+    fn missing_markers_are_warnings() {
+        let maze = maze();
+
+        // Same walls without the start and goal markers
+        let mut unmarked = Maze::<4, 4>::new();
+
+        for x in 0..4 {
+            for y in 0..4 {
+                let cell = Cell { x, y };
+
+                unmarked.set_cell_state(cell, maze.get_cell_state(cell));
+            }
+        }
+
+        let diagnostics = MazeValidator::new(&unmarked).validate();
+
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Warning,
+            None,
+            "no start cell is marked, assuming (0, 0)"
+        ));
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Warning,
+            None,
+            "no goal cell is marked"
+        ));
+    }
+
+    #[test]
+    fn unreachable_goal_is_an_error() {
+        let mut maze = maze();
+
+        maze.update_cell_state(Cell { x: 3, y: 2 }, CellState::NorthWall, true);
+
+        let diagnostics = MazeValidator::new(&maze).validate();
+
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Error,
+            None,
+            "goal is not reachable from the start cell"
+        ));
+        assert!(has_diagnostic(
+            &diagnostics,
+            Severity::Warning,
+            None,
+            "4 cells are not reachable from the start cell"
+        ));
+    }
+}
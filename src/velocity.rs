@@ -1,6 +1,9 @@
 use anyhow::Result;
 use std::{
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread::sleep,
     time::Duration,
 };
@@ -35,8 +38,9 @@ impl<const R: usize> VelocityEnvironment<R> {
         }
     }
 
-    pub fn process(self) -> Result<()> {
-        loop {
+    /// Integrates the velocity until `stop` is set
+    pub fn process(self, stop: Arc<AtomicBool>) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
             {
                 let mut runner_position = self.runner_position.lock().unwrap();
 
@@ -53,5 +57,7 @@ impl<const R: usize> VelocityEnvironment<R> {
 
             sleep(Duration::from_micros(100));
         }
+
+        Ok(())
     }
 }
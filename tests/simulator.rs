@@ -0,0 +1,90 @@
+use maze_simulator::{
+    maze::{Cell, CellState, Maze},
+    protocol::{MazeRunnerRequest, MazeRunnerResponse, MotionProfile, TurnDirection},
+    simulator::MazeSimulator,
+};
+
+const FAST: MotionProfile = MotionProfile {
+    max_speed: 2000.0,
+    acceleration: 20000.0,
+    end_speed: 0.0,
+};
+
+/// Walled 4x4 maze with the start in the bottom left corner and the goal two cells north
+fn simulator() -> MazeSimulator<4, 4> {
+    let mut maze = Maze::<4, 4>::new();
+
+    for i in 0..4 {
+        maze.update_cell_state(Cell::new(i, 0).unwrap(), CellState::SouthWall, true);
+        maze.update_cell_state(Cell::new(i, 3).unwrap(), CellState::NorthWall, true);
+        maze.update_cell_state(Cell::new(0, i).unwrap(), CellState::WestWall, true);
+        maze.update_cell_state(Cell::new(3, i).unwrap(), CellState::EastWall, true);
+    }
+
+    maze.update_cell_state(Cell::new(0, 0).unwrap(), CellState::EastWall, true);
+    maze.set_start_cell(Cell::new(0, 0).unwrap());
+    maze.set_goal_cell(Cell::new(0, 2).unwrap());
+
+    let mut simulator = MazeSimulator::builder(maze).build().unwrap();
+
+    assert!(matches!(
+        simulator.request(MazeRunnerRequest::Initialize).unwrap(),
+        MazeRunnerResponse::Ack
+    ));
+
+    simulator
+}
+
+#[test]
+fn profiled_move_reaches_the_goal() {
+    let mut simulator = simulator();
+
+    let response = simulator
+        .request(MazeRunnerRequest::MoveProfiled {
+            steps: 4,
+            profile: FAST,
+        })
+        .unwrap();
+
+    assert!(matches!(response, MazeRunnerResponse::Ack));
+    assert_eq!(simulator.get_runner_cell(), Cell::new(0, 2).unwrap());
+
+    let run_log = simulator.get_run_log();
+    let run_log = run_log.lock().unwrap();
+
+    assert_eq!(run_log.get_runs().len(), 1);
+    assert_eq!(run_log.get_crash_count(), 0);
+}
+
+#[test]
+fn move_into_a_wall_crashes() {
+    let mut simulator = simulator();
+
+    let response = simulator
+        .request(MazeRunnerRequest::Rotate90Profiled {
+            direction: TurnDirection::Right,
+            profile: MotionProfile {
+                max_speed: 20.0,
+                acceleration: 200.0,
+                end_speed: 0.0,
+            },
+        })
+        .unwrap();
+
+    assert!(matches!(response, MazeRunnerResponse::Ack));
+
+    let response = simulator
+        .request(MazeRunnerRequest::MoveProfiled {
+            steps: 2,
+            profile: FAST,
+        })
+        .unwrap();
+
+    assert!(matches!(response, MazeRunnerResponse::Error));
+
+    let run_log = simulator.get_run_log();
+    let run_log = run_log.lock().unwrap();
+
+    assert!(run_log.get_runs().is_empty());
+    assert_eq!(run_log.get_crash_count(), 1);
+}